@@ -0,0 +1,141 @@
+//! Credentials for private forges
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::PuppetfileError;
+use config::{ConfigLine, parse_line};
+use ErrorKind::InvalidForgeConfig;
+use transport::Request;
+
+/// A secret that `Debug` never prints
+#[derive(PartialEq, Eq, Clone)]
+pub struct Token(String);
+
+impl Token {
+    /// Wraps the secret
+    pub fn new(secret: &str) -> Token {
+        Token(secret.to_string())
+    }
+
+    /// The secret itself, only to be sent to the forge
+    pub fn secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Token(<redacted>)")
+    }
+}
+
+/// How requests to a forge are authenticated
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ForgeAuth {
+    /// Sent as `Authorization: Bearer <token>`
+    pub token: Option<Token>,
+    /// Sent instead of the default `User-Agent`
+    pub user_agent: Option<String>,
+}
+
+impl ForgeAuth {
+    /// Adds the `Authorization` and `User-Agent` headers to the request
+    pub fn apply(&self, mut request: Request) -> Request {
+        if let Some(ref token) = self.token {
+            request = request.with_header("Authorization", &format!("Bearer {}", token.secret()));
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            request = request.with_header("User-Agent", user_agent);
+        }
+        request
+    }
+}
+
+/// The credentials of every configured forge
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ForgeCredentials {
+    forges: HashMap<String, ForgeAuth>,
+}
+
+impl ForgeCredentials {
+    /// Parses a config file with one `["forge url"]` section per forge
+    ///
+    /// A section sets `token`, `token_env` naming an environment variable holding the
+    /// token, or `user_agent`. `#` outside of quotes starts a comment, e.g.
+    ///
+    /// ```toml
+    /// ["https://forge.example.com"]
+    /// token_env = "FORGE_TOKEN"
+    /// user_agent = "deploy/1.0"
+    /// ```
+    pub fn parse(contents: &str) -> Result<ForgeCredentials, PuppetfileError> {
+        let mut credentials = ForgeCredentials::default();
+        let mut forge: Option<String> = None;
+        for (i, line) in contents.lines().enumerate() {
+            // never include the line itself, it may contain a token
+            let invalid = |reason: String| -> PuppetfileError {
+                From::from((InvalidForgeConfig(i + 1),
+                            format!("invalid forge config on line {}: {}", i + 1, reason)))
+            };
+            let (key, value) = match parse_line(line) {
+                Ok(ConfigLine::Blank) => continue,
+                Ok(ConfigLine::Section(url)) => {
+                    credentials.forges.entry(forge_key(&url)).or_insert(ForgeAuth::default());
+                    forge = Some(forge_key(&url));
+                    continue;
+                }
+                Ok(ConfigLine::Pair(key, value)) => (key, value),
+                Err(reason) => return Err(invalid(reason.to_string())),
+            };
+            let auth = match forge {
+                Some(ref forge) => credentials.forges.get_mut(forge).unwrap(),
+                None => return Err(invalid(format!("`{}` outside of a forge section", key))),
+            };
+            match &key[..] {
+                "token" => auth.token = Some(Token::new(&value)),
+                "token_env" => {
+                    match env::var(&value) {
+                        Ok(token) => auth.token = Some(Token::new(&token)),
+                        Err(..) => {
+                            return Err(invalid(format!("environment variable `{}` is not set",
+                                                       value)))
+                        }
+                    }
+                }
+                "user_agent" => auth.user_agent = Some(value),
+                _ => {
+                    return Err(invalid(format!("unknown key `{}`, expected `token`, \
+                                                `token_env` or `user_agent`",
+                                               key)))
+                }
+            }
+        }
+        Ok(credentials)
+    }
+
+    /// Reads and parses a config file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ForgeCredentials, PuppetfileError> {
+        let mut contents = String::new();
+        try!(try!(File::open(path)).read_to_string(&mut contents));
+        ForgeCredentials::parse(&contents)
+    }
+
+    /// The credentials for the forge, anonymous if it is not configured
+    pub fn get(&self, forge_url: &str) -> ForgeAuth {
+        self.forges.get(&forge_key(forge_url)).cloned().unwrap_or(ForgeAuth::default())
+    }
+
+    /// Sets the credentials for the forge
+    pub fn set(&mut self, forge_url: &str, auth: ForgeAuth) {
+        self.forges.insert(forge_key(forge_url), auth);
+    }
+}
+
+fn forge_key(forge_url: &str) -> String {
+    forge_url.trim_right_matches('/').to_string()
+}
@@ -0,0 +1,73 @@
+#![allow(deprecated, clippy::redundant_static_lifetimes)]
+
+extern crate puppetfile;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+
+use puppetfile::{SyntaxTree, FormatStyle, HashStyle, Renderer, line_diff};
+
+const USAGE: &'static str = "usage: puppetfile-fmt [--check] [--double-quotes] [--ruby19 | \
+                             --hash-rocket] [--no-align] [PUPPETFILE...]";
+
+fn main() {
+    let mut check = false;
+    let mut style = FormatStyle::default();
+    let mut paths = vec![];
+    for arg in env::args().skip(1) {
+        match &arg[..] {
+            "--check" => check = true,
+            "--double-quotes" => style.quote = '"',
+            "--ruby19" => style.hash_style = Some(HashStyle::Ruby19),
+            "--hash-rocket" => style.hash_style = Some(HashStyle::HashRocket),
+            "--no-align" => style.align = false,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("-") => {
+                let _ = writeln!(std::io::stderr(), "unknown option {}\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("Puppetfile".to_string());
+    }
+
+    let mut failed = false;
+    for path in paths.iter() {
+        let mut contents = String::new();
+        if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            let _ = writeln!(std::io::stderr(), "error: could not read {}: {}", path, err);
+            failed = true;
+            continue;
+        }
+        let tree = match SyntaxTree::parse(&contents) {
+            Ok(tree) => tree,
+            Err(err) => {
+                let _ = write!(std::io::stderr(), "{}", Renderer::new(path, &contents).render(&err));
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = tree.format(&style);
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            println!("Diff in {}:\n{}", path, line_diff(&contents, &formatted));
+            failed = true;
+        } else if let Err(err) = File::create(path)
+                                     .and_then(|mut file| file.write_all(formatted.as_bytes())) {
+            let _ = writeln!(std::io::stderr(), "error: could not write {}: {}", path, err);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,77 @@
+#![allow(deprecated, clippy::redundant_static_lifetimes)]
+
+extern crate puppetfile;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+
+use puppetfile::{LintConfig, Renderer, Severity};
+use puppetfile::lint;
+
+const USAGE: &'static str = "usage: puppetfile-lint [--config FILE] [PUPPETFILE...]";
+
+/// Read when no `--config` is given and the file exists
+const DEFAULT_CONFIG: &'static str = ".puppetfile-lint.toml";
+
+fn fail(message: &str) -> ! {
+    let _ = writeln!(std::io::stderr(), "{}", message);
+    process::exit(2);
+}
+
+fn main() {
+    let mut config_path = None;
+    let mut paths = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--config" => {
+                config_path = Some(args.next().unwrap_or_else(|| fail(USAGE)));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("-") => fail(&format!("unknown option {}\n{}", arg, USAGE)),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("Puppetfile".to_string());
+    }
+    if config_path.is_none() && Path::new(DEFAULT_CONFIG).exists() {
+        config_path = Some(DEFAULT_CONFIG.to_string());
+    }
+    let config = match config_path {
+        Some(path) => LintConfig::load(&path).unwrap_or_else(|err| fail(&err.desc)),
+        None => LintConfig::default(),
+    };
+
+    let mut failed = false;
+    for path in paths.iter() {
+        let mut contents = String::new();
+        if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+            let _ = writeln!(std::io::stderr(), "error: could not read {}: {}", path, err);
+            failed = true;
+            continue;
+        }
+        let renderer = Renderer::new(path, &contents);
+        match lint::lint(&contents, &config) {
+            Ok(lints) => {
+                for lint in lints.iter() {
+                    println!("{}", renderer.render_lint(lint));
+                    failed |= lint.severity == Severity::Error;
+                }
+            }
+            Err(err) => {
+                println!("{}", renderer.render(&err));
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,259 @@
+//! An on-disk cache for forge responses
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::PuppetfileError;
+use ErrorKind::*;
+use transport::{Transport, Request, Response, url_path};
+
+/// How long a cached response is used without asking the forge again
+pub const DEFAULT_TTL: u64 = 60 * 60;
+
+/// Makes the names of temporary files unique within the process
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A transport storing successful responses on disk, one file per forge and path
+///
+/// Fresh responses are answered from the cache, stale ones are revalidated with
+/// `If-None-Match` and `If-Modified-Since`. If the forge cannot be reached, answers
+/// with a server error or rate limits the client, a stale response is used instead. In
+/// offline mode only the cache is used. Failing to write the cache is not an error.
+/// Responses to requests with an `Authorization` header are kept apart per credential.
+pub struct CachingTransport<T> {
+    inner: T,
+    dir: PathBuf,
+    ttl: Duration,
+    offline: bool,
+}
+
+struct Entry {
+    fetched: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+impl<T: Transport> CachingTransport<T> {
+    /// Caches the responses of the transport in the directory
+    pub fn new<P: AsRef<Path>>(inner: T, dir: P) -> CachingTransport<T> {
+        CachingTransport {
+            inner: inner,
+            dir: dir.as_ref().to_path_buf(),
+            ttl: Duration::from_secs(DEFAULT_TTL),
+            offline: false,
+        }
+    }
+
+    /// Sets how long a cached response is used without asking the forge again
+    pub fn with_ttl(mut self, ttl: Duration) -> CachingTransport<T> {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Answers only from the cache, without sending any request
+    ///
+    /// Authenticated requests are answered from the responses cached for the same credential.
+    pub fn with_offline(mut self, offline: bool) -> CachingTransport<T> {
+        self.offline = offline;
+        self
+    }
+
+    /// The transport requests are sent through
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// The file the response for the URL is cached in
+    pub fn cache_path(&self, url: &str) -> PathBuf {
+        self.entry_path(url, None)
+    }
+
+    /// The file the response for the URL is cached in for requests with the `Authorization`
+    /// header, only a hash of the credential ends up in the path
+    pub fn credential_cache_path(&self, url: &str, authorization: &str) -> PathBuf {
+        self.entry_path(url, Some(authorization))
+    }
+
+    fn entry_path(&self, url: &str, authorization: Option<&str>) -> PathBuf {
+        let path = url_path(url);
+        let forge = &url[..url.len() - path.len()];
+        let forge = match forge.find("://") {
+            Some(i) => &forge[i + 3..],
+            None => forge,
+        };
+        let mut dir = file_name(forge);
+        if let Some(authorization) = authorization {
+            dir.push_str(&format!("@{:016x}", fnv1a(authorization)));
+        }
+        self.dir.join(dir).join(file_name(path))
+    }
+}
+
+impl<T: Transport> Transport for CachingTransport<T> {
+    fn send(&self, request: &Request) -> Result<Response, PuppetfileError> {
+        // responses for credentials must not be shared between them
+        let path = self.entry_path(&request.url, request.header("Authorization"));
+        let cached = Entry::read(&path);
+        if self.offline {
+            return cached.map(|entry| entry.response()).ok_or_else(|| not_cached(&request.url));
+        }
+        let mut conditional = request.clone();
+        if let Some(ref entry) = cached {
+            if Duration::from_secs(now().saturating_sub(entry.fetched)) < self.ttl {
+                return Ok(entry.response());
+            }
+            if let Some(ref etag) = entry.etag {
+                conditional = conditional.with_header("If-None-Match", etag);
+            }
+            if let Some(ref last_modified) = entry.last_modified {
+                conditional = conditional.with_header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = match self.inner.send(&conditional) {
+            Ok(response) => response,
+            Err(err) => {
+                return match cached {
+                    Some(entry) => Ok(entry.response()),
+                    None => Err(err),
+                }
+            }
+        };
+        if let Some(mut entry) = cached {
+            if response.status == 304 {
+                entry.fetched = now();
+                // the cache is best effort, a failed write only costs a request later
+                let _ = entry.write(&path);
+                return Ok(entry.response());
+            }
+            if response.status >= 500 || response.status == 429 {
+                return Ok(entry.response());
+            }
+        }
+        if response.status == 200 {
+            let entry = Entry {
+                fetched: now(),
+                etag: response.header("ETag").map(|etag| etag.to_string()),
+                last_modified: response.header("Last-Modified").map(|date| date.to_string()),
+                body: response.body.clone(),
+            };
+            let _ = entry.write(&path);
+        }
+        Ok(response)
+    }
+}
+
+impl Entry {
+    /// Reads a cached response, `None` if it is missing or unreadable
+    fn read(path: &Path) -> Option<Entry> {
+        let mut contents = String::new();
+        if File::open(path).and_then(|mut file| file.read_to_string(&mut contents)).is_err() {
+            return None;
+        }
+        let end = match contents.find("\n\n") {
+            Some(end) => end,
+            None => return None,
+        };
+        let mut entry = Entry {
+            fetched: 0,
+            etag: None,
+            last_modified: None,
+            body: contents[end + 2..].to_string(),
+        };
+        for line in contents[..end].lines() {
+            let (name, value) = match line.find(": ") {
+                Some(i) => (&line[..i], line[i + 2..].to_string()),
+                None => return None,
+            };
+            match name {
+                "fetched" => entry.fetched = match value.parse() {
+                    Ok(fetched) => fetched,
+                    Err(..) => return None,
+                },
+                "etag" => entry.etag = Some(value),
+                "last-modified" => entry.last_modified = Some(value),
+                _ => {}
+            }
+        }
+        Some(entry)
+    }
+
+    /// Writes to a temporary file next to the entry and renames it, so concurrent
+    /// readers never see a partial entry
+    fn write(&self, path: &Path) -> Result<(), PuppetfileError> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(format!(".{}.{}.tmp",
+                          process::id(),
+                          TEMP_FILES.fetch_add(1, Ordering::SeqCst)));
+        let temp = PathBuf::from(temp);
+        let written = self.write_to(&temp).and_then(|_| fs::rename(&temp, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written.map_err(From::from)
+    }
+
+    fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        try!(writeln!(file, "fetched: {}", self.fetched));
+        if let Some(ref etag) = self.etag {
+            try!(writeln!(file, "etag: {}", etag));
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            try!(writeln!(file, "last-modified: {}", last_modified));
+        }
+        write!(file, "\n{}", self.body)
+    }
+
+    fn response(&self) -> Response {
+        let mut headers = vec![];
+        if let Some(ref etag) = self.etag {
+            headers.push(("ETag".to_string(), etag.clone()));
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            headers.push(("Last-Modified".to_string(), last_modified.clone()));
+        }
+        Response {
+            status: 200,
+            headers: headers,
+            body: self.body.clone(),
+        }
+    }
+}
+
+fn not_cached(url: &str) -> PuppetfileError {
+    From::from((NotCached(url.to_string()),
+                format!("{} is not cached and the forge client is offline", url)))
+}
+
+/// Seconds since the epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0)
+}
+
+/// The 64 bit FNV-1a hash, which unlike `DefaultHasher` is stable across releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Escapes everything but letters, digits, `.` and `-`, e.g. `/` becomes `%2F`
+fn file_name(part: &str) -> String {
+    let mut name = String::new();
+    for byte in part.bytes() {
+        match byte {
+            b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'.' | b'-' => name.push(byte as char),
+            _ => name.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    name
+}
@@ -0,0 +1,120 @@
+//! Checking all forge modules of a Puppetfile for new versions
+
+use std::cmp;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+
+use semver;
+
+use super::{Puppetfile, Module, ModuleSource, PuppetfileError};
+use forge::{ForgeClient, api_url};
+use ErrorKind::LookupPanicked;
+use transport::Transport;
+
+/// The number of modules `check_versions` looks up at once
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The current forge version of a module
+#[derive(Debug)]
+pub struct VersionCheck {
+    /// The module as declared in the Puppetfile
+    pub module: Module,
+    /// The most recent version on the forge, or why it could not be looked up
+    pub latest: Result<semver::Version, PuppetfileError>,
+}
+
+impl VersionCheck {
+    /// Whether the latest version does not satisfy the declared version,
+    /// `None` if the lookup failed or the module declares no version
+    pub fn is_outdated(&self) -> Option<bool> {
+        match (self.module.version(), self.latest.as_ref()) {
+            (Some(requirement), Ok(latest)) => Some(!requirement.matches(latest)),
+            _ => None,
+        }
+    }
+}
+
+impl Puppetfile {
+    /// Looks up the forge version of every forge module on the forge of the Puppetfile
+    ///
+    /// A declared `https://forge.puppetlabs.com` is looked up on its API host.
+    pub fn check_versions(&self, concurrency: usize) -> Vec<VersionCheck> {
+        let client = ForgeClient::new(api_url(self.forge_url()));
+        self.check_versions_with(Arc::new(client), concurrency)
+    }
+
+    /// Looks up the forge version of every forge module with at most `concurrency` lookups
+    /// at once, the checks are in the order of the modules
+    pub fn check_versions_with<T>(&self,
+                                  client: Arc<ForgeClient<T>>,
+                                  concurrency: usize)
+                                  -> Vec<VersionCheck>
+        where T: Transport + Send + Sync + 'static
+    {
+        let modules: Vec<Module> = self.modules
+                                       .iter()
+                                       .filter(|module| match module.source() {
+                                           Ok(ModuleSource::Forge(..)) => true,
+                                           _ => false,
+                                       })
+                                       .cloned()
+                                       .collect();
+        let workers = cmp::max(1, cmp::min(concurrency, modules.len()));
+        let queue = Arc::new(Mutex::new(modules.clone().into_iter().enumerate()));
+        let (sender, receiver) = mpsc::channel();
+        let mut handles = vec![];
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            let client = client.clone();
+            handles.push(thread::spawn(move || {
+                loop {
+                    let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+                    let (i, module) = match next {
+                        Some(next) => next,
+                        None => return,
+                    };
+                    let latest = panic::catch_unwind(AssertUnwindSafe(|| {
+                                     module.forge_version_with(&client)
+                                 }))
+                                 .unwrap_or_else(|_| Err(panicked(&module)));
+                    let check = VersionCheck {
+                        module: module,
+                        latest: latest,
+                    };
+                    if sender.send((i, check)).is_err() {
+                        return;
+                    }
+                }
+            }));
+        }
+        drop(sender);
+
+        let mut checks: Vec<Option<VersionCheck>> = modules.iter().map(|_| None).collect();
+        for (i, check) in receiver.iter() {
+            checks[i] = Some(check);
+        }
+        for handle in handles {
+            let _ = handle.join();
+        }
+        // a module without a check lost its worker
+        checks.into_iter()
+              .zip(modules)
+              .map(|(check, module)| {
+                  check.unwrap_or_else(|| {
+                      VersionCheck {
+                          latest: Err(panicked(&module)),
+                          module: module,
+                      }
+                  })
+              })
+              .collect()
+    }
+}
+
+fn panicked(module: &Module) -> PuppetfileError {
+    From::from((LookupPanicked(module.name.clone()),
+                format!("the version lookup of module '{}' panicked", module.name)))
+}
@@ -0,0 +1,143 @@
+//! Modules that are declared more than once or install into the same directory
+
+use std::fmt;
+use std::path::PathBuf;
+
+use super::{Puppetfile, Module, PuppetfileError};
+use name::same_module;
+use syntax::{SyntaxTree, Location};
+
+/// Why a declaration collides with an earlier one
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CollisionKind {
+    /// The same module is declared again, r10k silently keeps the last declaration
+    Duplicate,
+    /// A different module installs into the same directory, e.g. `a/apache` and `b/apache`
+    InstallPath,
+}
+
+/// A module declaration that collides with an earlier one
+#[derive(PartialEq, Clone, Debug)]
+pub struct Collision {
+    /// Why the declarations collide
+    pub kind: CollisionKind,
+    /// The directory the later module installs into, relative to the Puppetfile
+    pub install_path: PathBuf,
+    /// Name of the earlier module
+    pub first: String,
+    /// Location of the name of the earlier module, `None` without source
+    pub first_location: Option<Location>,
+    /// Name of the later module
+    pub second: String,
+    /// Location of the name of the later module, `None` without source
+    pub second_location: Option<Location>,
+}
+
+impl Collision {
+    /// Describes the collision without locations
+    pub fn message(&self) -> String {
+        match self.kind {
+            CollisionKind::Duplicate => duplicate_message(&self.first, &self.second),
+            CollisionKind::InstallPath => {
+                format!("module '{}' installs into '{}' like '{}'",
+                        self.second,
+                        self.install_path.display(),
+                        self.first)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}", self.message()));
+        if let (Some(first), Some(second)) = (self.first_location, self.second_location) {
+            match self.kind {
+                CollisionKind::Duplicate => {
+                    try!(write!(f,
+                                " (duplicate at {}:{}, original at {}:{})",
+                                second.start.line,
+                                second.start.column,
+                                first.start.line,
+                                first.start.column))
+                }
+                CollisionKind::InstallPath => {
+                    try!(write!(f,
+                                " (declared at {}:{}, '{}' declared at {}:{})",
+                                second.start.line,
+                                second.start.column,
+                                self.first,
+                                first.start.line,
+                                first.start.column))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Puppetfile {
+    /// Parses a Puppetfile and returns all declarations colliding with an earlier one
+    pub fn validate(contents: &str) -> Result<(Puppetfile, Vec<Collision>), PuppetfileError> {
+        let tree = try!(SyntaxTree::parse(contents));
+        let puppetfile = try!(tree.to_puppetfile());
+        let locations: Vec<_> = tree.module_locations()
+                                    .into_iter()
+                                    .map(|location| Some(location.name))
+                                    .collect();
+        let collisions = collisions(&puppetfile, &locations);
+        Ok((puppetfile, collisions))
+    }
+
+    /// Returns all declarations colliding with an earlier one, without locations
+    pub fn collisions(&self) -> Vec<Collision> {
+        let locations = vec![None; self.modules.len()];
+        collisions(self, &locations)
+    }
+}
+
+fn collisions(puppetfile: &Puppetfile, locations: &[Option<Location>]) -> Vec<Collision> {
+    let paths: Vec<PathBuf> = puppetfile.modules
+                                        .iter()
+                                        .map(|module| puppetfile.install_path(module))
+                                        .collect();
+    let mut collisions = vec![];
+    for (i, second) in puppetfile.modules.iter().enumerate() {
+        let earlier: Vec<&Module> = puppetfile.modules[..i].iter().collect();
+        let first = match duplicate_of(second, &earlier) {
+            Some(first) => Some((first, CollisionKind::Duplicate)),
+            None => {
+                (0..i).find(|&first| paths[first] == paths[i])
+                      .map(|first| (first, CollisionKind::InstallPath))
+            }
+        };
+        let (first, kind) = match first {
+            Some(first) => first,
+            None => continue,
+        };
+        collisions.push(Collision {
+            kind: kind,
+            install_path: paths[i].clone(),
+            first: puppetfile.modules[first].name.clone(),
+            first_location: locations[first],
+            second: second.name.clone(),
+            second_location: locations[i],
+        });
+    }
+    collisions
+}
+
+/// The index of the first earlier module that is the same module, `a/b` and `a-b` are the
+/// same
+pub fn duplicate_of(module: &Module, earlier: &[&Module]) -> Option<usize> {
+    earlier.iter().position(|other| same_module(&other.name, &module.name))
+}
+
+/// Describes a module declared again, possibly under another spelling of its name
+pub fn duplicate_message(first: &str, second: &str) -> String {
+    if first == second {
+        format!("module '{}' is declared more than once", second)
+    } else {
+        format!("module '{}' is already declared as '{}'", second, first)
+    }
+}
@@ -0,0 +1,77 @@
+//! The line format shared by the lint and forge config files
+//!
+//! A small subset of TOML: `[section]` headers, `key = value` pairs with quoted or bare
+//! values, and `#` comments outside of quoted strings.
+
+/// A parsed config line
+#[derive(PartialEq, Clone, Debug)]
+pub enum ConfigLine {
+    /// An empty line or a comment
+    Blank,
+    /// `[name]` or `["name"]`
+    Section(String),
+    /// `key = value` or `key = "value"`
+    Pair(String, String),
+}
+
+/// Parses one line, the error is a reason that never includes the line itself
+pub fn parse_line(line: &str) -> Result<ConfigLine, &'static str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(ConfigLine::Blank);
+    }
+    if let Some(section) = line.strip_prefix('[') {
+        let (name, rest) = try!(value(section, ']'));
+        match rest.strip_prefix(']') {
+            Some(rest) => try!(end_of_line(rest)),
+            None => return Err("expected `]` after the section name"),
+        }
+        return Ok(ConfigLine::Section(name));
+    }
+    let equals = match line.find('=') {
+        Some(equals) => equals,
+        None => return Err("expected `key = \"value\"`"),
+    };
+    let key = line[..equals].trim();
+    if key.is_empty() || key.contains('"') || key.contains('#') {
+        return Err("expected `key = \"value\"`");
+    }
+    let (value, rest) = try!(value(&line[equals + 1..], '#'));
+    try!(end_of_line(rest));
+    Ok(ConfigLine::Pair(key.to_string(), value))
+}
+
+/// Reads a quoted string or a bare value ending before `end`, returns it and the rest
+fn value(input: &str, end: char) -> Result<(String, &str), &'static str> {
+    let input = input.trim_left();
+    if !input.starts_with('"') {
+        let len = input.find(end).unwrap_or(input.len());
+        return Ok((input[..len].trim().to_string(), &input[len..]));
+    }
+    let mut value = String::new();
+    let mut chars = input.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, input[i + 1..].trim_left())),
+            '\\' => {
+                match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    _ => return Err("unknown escape in quoted string"),
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    Err("unterminated quoted string")
+}
+
+/// Checks that only a comment follows
+fn end_of_line(rest: &str) -> Result<(), &'static str> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err("unexpected text after the value")
+    }
+}
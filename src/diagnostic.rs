@@ -0,0 +1,222 @@
+//! Rustc-style rendering of errors together with the offending source line
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::{PuppetfileError, Collision};
+use lint::Lint;
+use ErrorKind::{ParseError, DuplicateDirective, InvalidVersion, InvalidSource};
+
+/// Renders errors and lints with the file name, the offending source line and a caret under the column
+///
+/// ```text
+/// error: expected one of `,`, `forge`, `mod`, `moduledir`
+///  --> Puppetfile:2:20
+///   |
+/// 2 | mod 'nginx', '1.0' :git => 'https://github.com/voxpupuli/puppet-nginx.git'
+///   |                    ^^^^
+///   = help: did you mean `mod 'nginx', '1.0', :git ...`?
+/// ```
+pub struct Renderer<'a> {
+    name: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Renderer<'a> {
+    /// Creates a renderer for errors in `source`, which was read from the file `name`
+    pub fn new(name: &'a str, source: &'a str) -> Renderer<'a> {
+        Renderer {
+            name: name,
+            source: source,
+        }
+    }
+
+    /// Renders a single error, errors without a location only print their description
+    pub fn render(&self, err: &PuppetfileError) -> String {
+        let (message, line, column) = match err.kind {
+            ParseError(ref err) => (expected_message(&err.expected), err.line, err.column),
+            InvalidVersion { ref version, location } => {
+                (format!("invalid version '{}'", version),
+                 location.start.line,
+                 location.start.column)
+            }
+            InvalidSource { location: Some(location), .. } => {
+                (err.desc.clone(), location.start.line, location.start.column)
+            }
+            DuplicateDirective { ref directive, line, column } => {
+                (format!("`{}` declared a second time", directive), line, column)
+            }
+            _ => return format!("error: {}\n", err.desc),
+        };
+        let help = match err.kind {
+            ParseError(ref err) => {
+                let text = self.source.lines().nth(line - 1).unwrap_or("");
+                missing_comma(&err.expected, text, byte_offset(text, column))
+            }
+            _ => None,
+        };
+        self.snippet("error", &message, line, column, help)
+    }
+
+    /// Renders a lint, lints without a location only print their message
+    pub fn render_lint(&self, lint: &Lint) -> String {
+        let level = format!("{}[{}]", lint.severity, lint.rule);
+        match lint.location {
+            Some(location) => {
+                self.snippet(&level,
+                             &lint.message,
+                             location.start.line,
+                             location.start.column,
+                             None)
+            }
+            None => format!("{}: {}\n", level, lint.message),
+        }
+    }
+
+    /// Renders a collision as a warning pointing at both declarations
+    pub fn render_collision(&self, collision: &Collision) -> String {
+        match (collision.first_location, collision.second_location) {
+            (Some(first), Some(second)) => {
+                let mut out = self.snippet("warning",
+                                           &collision.message(),
+                                           second.start.line,
+                                           second.start.column,
+                                           None);
+                out.push_str(&self.snippet("note",
+                                           &format!("'{}' is declared here", collision.first),
+                                           first.start.line,
+                                           first.start.column,
+                                           None));
+                out
+            }
+            _ => format!("warning: {}\n", collision.message()),
+        }
+    }
+
+    fn snippet(&self,
+               level: &str,
+               message: &str,
+               line: usize,
+               column: usize,
+               help: Option<String>)
+               -> String {
+        let text = self.source.lines().nth(line - 1).unwrap_or("");
+        let offset = byte_offset(text, column);
+        let gutter = spaces(line.to_string().len());
+        let indent: String = text[..offset]
+                                 .chars()
+                                 .map(|c| if c == '\t' { '\t' } else { ' ' })
+                                 .collect();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}: {}", level, message);
+        let _ = writeln!(out, "{}--> {}:{}:{}", gutter, self.name, line, column);
+        let _ = writeln!(out, "{} |", gutter);
+        let _ = writeln!(out, "{} | {}", line, text);
+        let _ = writeln!(out,
+                         "{} | {}{}",
+                         gutter,
+                         indent,
+                         "^".repeat(token_len(&text[offset..])));
+        if let Some(help) = help {
+            let _ = writeln!(out, "{} = help: {}", gutter, help);
+        }
+        out
+    }
+
+    /// Renders all errors separated by blank lines
+    pub fn render_all(&self, errors: &[PuppetfileError]) -> String {
+        errors.iter().map(|err| self.render(err)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Lists the expected tokens in a stable order, leaving out whitespace and comments
+fn expected_message(expected: &HashSet<&'static str>) -> String {
+    let mut tokens: Vec<_> = expected.iter()
+                                     .cloned()
+                                     .filter(|token| {
+                                         !(token.trim().is_empty() || *token == "#" ||
+                                           token.starts_with("[ ") ||
+                                           token.starts_with("[\n"))
+                                     })
+                                     .collect();
+    tokens.sort();
+    let tokens: Vec<_> = tokens.iter().map(|token| format!("`{}`", token)).collect();
+    match tokens.len() {
+        0 => "unexpected input".to_string(),
+        1 => format!("expected {}", tokens[0]),
+        _ => format!("expected one of {}", tokens.join(", ")),
+    }
+}
+
+/// Suggests a comma if an option follows a value without one
+fn missing_comma(expected: &HashSet<&'static str>, text: &str, offset: usize) -> Option<String> {
+    let rest = &text[offset..];
+    if !expected.contains(",") || !starts_option(rest) {
+        return None;
+    }
+    let before = text[..offset].trim_right();
+    if before.is_empty() {
+        return Some("the previous line might be missing a `,` at its end".to_string());
+    }
+    if !before.ends_with(|c: char| c == '\'' || c == '"' || c == '_' || c.is_alphanumeric()) {
+        return None;
+    }
+    let next_token = &rest[..rest.find(" =>").unwrap_or(token_len_bytes(rest))];
+    let ellipsis = if next_token.len() < rest.trim_right().len() { " ..." } else { "" };
+    Some(format!("did you mean `{}, {}{}`?", before.trim_left(), next_token, ellipsis))
+}
+
+/// Whether `rest` starts with a quoted string, a `:symbol` or a `label:`
+fn starts_option(rest: &str) -> bool {
+    let word = |text: &str| {
+        text.starts_with(|c: char| c == '_' || c.is_alphabetic())
+    };
+    if rest.starts_with('\'') || rest.starts_with('"') {
+        return true;
+    }
+    if let Some(symbol) = rest.strip_prefix(':') {
+        return word(symbol);
+    }
+    let len = rest.find(|c: char| !(c == '_' || c.is_alphanumeric())).unwrap_or(rest.len());
+    word(rest) && rest[len..].starts_with(':') && !rest[len..].starts_with("::")
+}
+
+/// Length in characters of the quoted string, symbol or word at the start of `rest`
+fn token_len(rest: &str) -> usize {
+    match rest[..token_len_bytes(rest)].chars().count() {
+        0 => 1,
+        len => len,
+    }
+}
+
+fn token_len_bytes(rest: &str) -> usize {
+    let mut chars = rest.char_indices();
+    match chars.next() {
+        Some((_, quote)) if quote == '\'' || quote == '"' => {
+            let mut escaped = false;
+            for (i, c) in chars {
+                if c == quote && !escaped {
+                    return i + 1;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            rest.len()
+        }
+        Some((_, c)) if c == ':' || c == '_' || c.is_alphanumeric() => {
+            rest.find(|c: char| !(c == ':' || c == '_' || c.is_alphanumeric()))
+                .unwrap_or(rest.len())
+        }
+        Some((_, c)) => c.len_utf8(),
+        None => 0,
+    }
+}
+
+/// Converts a one-based column into a byte offset in `text`
+fn byte_offset(text: &str, column: usize) -> usize {
+    text.char_indices().nth(column - 1).map(|(i, _)| i).unwrap_or(text.len())
+}
+
+fn spaces(len: usize) -> String {
+    " ".repeat(len)
+}
@@ -0,0 +1,328 @@
+//! Format-preserving edits of a Puppetfile
+//!
+//! Every edit only rewrites the text of the affected statement, all other statements and the
+//! comments around them are kept as they are.
+
+use std::cmp;
+
+use super::{Module, PuppetfileError, HashStyle, Value};
+use name::same_module;
+use sort::{SortOrder, sort_key};
+use syntax::{SyntaxTree, Node, NodeKind, ModuleNode, OptionKind, ValueNode, Span};
+use ErrorKind::{UnknownModule, ModuleExists};
+
+impl SyntaxTree {
+    /// Sets the version of the named module, adding one if the module has none
+    ///
+    /// Fails with `InvalidSource` if the module can not have a version, e.g. a `:git` module.
+    pub fn set_version(&mut self, name: &str, version: &str) -> Result<(), PuppetfileError> {
+        let index = try!(self.module_index(name));
+        let text = {
+            let node = &self.nodes[index];
+            let module = node.module().unwrap();
+            let quoted = quote(version, module.name.quote);
+            let current = module.options.iter().find(|option| {
+                match option.kind {
+                    OptionKind::Version(..) | OptionKind::Latest => true,
+                    OptionKind::Pair { .. } => false,
+                }
+            });
+            match current {
+                Some(option) => node.replace(option.span, &quoted),
+                None => {
+                    let end = module.name.span.end;
+                    node.replace(Span::new(end, end), &format!(", {}", quoted))
+                }
+            }
+        };
+        self.replace_checked(index, text, name)
+    }
+
+    /// Sets the option `key` of the named module, appending it if it is not present yet
+    ///
+    /// Fails with `InvalidSource` if the options then contradict each other, e.g. `:tag`
+    /// next to `:branch` or `:git` next to a version.
+    pub fn set_option<V: Into<Value>>(&mut self,
+                                      name: &str,
+                                      key: &str,
+                                      value: V)
+                                      -> Result<(), PuppetfileError> {
+        let value = value.into();
+        let index = try!(self.module_index(name));
+        let text = {
+            let node = &self.nodes[index];
+            let module = node.module().unwrap();
+            let mut style = HashStyle::HashRocket;
+            let mut current = None;
+            for option in module.options.iter() {
+                if let OptionKind::Pair { key: ref k, value: ref v, style: s } = option.kind {
+                    style = s;
+                    if k.name == key {
+                        current = Some(v);
+                    }
+                }
+            }
+            match current {
+                Some(current) => {
+                    let quote_char = match *current {
+                        ValueNode::String(ref s) => s.quote,
+                        ValueNode::Symbol(..) | ValueNode::Boolean { .. } => module.name.quote,
+                    };
+                    node.replace(current.span(), &value_text(&value, quote_char))
+                }
+                None => {
+                    let value = value_text(&value, module.name.quote);
+                    let pair = match style {
+                        HashStyle::HashRocket => format!(":{} => {}", key, value),
+                        HashStyle::Ruby19 => format!("{}: {}", key, value),
+                    };
+                    let end = node.span.end;
+                    node.replace(Span::new(end, end),
+                                 &format!("{}{}", node.option_separator(module), pair))
+                }
+            }
+        };
+        self.replace_checked(index, text, name)
+    }
+
+    /// Appends a module after the last `mod` statement
+    ///
+    /// The new statement is separated by as many line breaks as the last module is.
+    pub fn add_module(&mut self, module: &Module) -> Result<(), PuppetfileError> {
+        if self.module_index(&module.name).is_ok() {
+            return Err(From::from((ModuleExists(module.name.clone()),
+                                   format!("module '{}' is already declared", module.name))));
+        }
+        let index = self.nodes
+                        .iter()
+                        .rposition(|node| node.module().is_some())
+                        .map(|index| index + 1)
+                        .unwrap_or(self.nodes.len());
+        let mut source = String::new();
+        for node in self.nodes[..index].iter() {
+            source.push_str(&node.to_string());
+        }
+        if !source.is_empty() {
+            let breaks = match index.checked_sub(1).map(|last| &self.nodes[last].leading.text) {
+                Some(leading) => {
+                    leading.chars()
+                           .take_while(|c| c.is_whitespace())
+                           .filter(|&c| c == '\n')
+                           .count()
+                }
+                None => 1,
+            };
+            for _ in 0..cmp::max(breaks, 1) {
+                source.push('\n');
+            }
+        }
+        source.push_str(&module.to_string());
+        if index == self.nodes.len() && !self.trailing.text.starts_with("\n") {
+            source.push('\n');
+        }
+        for node in self.nodes[index..].iter() {
+            source.push_str(&node.to_string());
+        }
+        source.push_str(&self.trailing.text);
+        self.reparse(&source)
+    }
+
+    /// Removes the named module together with its comments
+    ///
+    /// Like `sort_modules` only the comment lines directly above the module and a comment
+    /// behind it are removed, comments separated from it by a blank line stay in place.
+    pub fn remove_module(&mut self, name: &str) -> Result<(), PuppetfileError> {
+        let index = try!(self.module_index(name));
+        let mut source = String::new();
+        for node in self.nodes[..index].iter() {
+            source.push_str(&node.to_string());
+        }
+        let leading = &self.nodes[index].leading.text;
+        let kept = &leading[..attached_comments(leading)];
+        source.push_str(kept);
+        let mut rest = String::new();
+        for node in self.nodes[index + 1..].iter() {
+            rest.push_str(&node.to_string());
+        }
+        rest.push_str(&self.trailing.text);
+        // drop the line break ending the removed statement, and the blank lines behind it if
+        // there are already blank lines in front of it
+        let mut rest = strip_line_break(&rest);
+        if source.is_empty() || kept.ends_with("\n\n") || kept.ends_with("\n\r\n") {
+            rest = rest.trim_left_matches(|c| c == '\r' || c == '\n');
+        }
+        source.push_str(rest);
+        self.reparse(&source)
+    }
+
+    /// Sorts the `mod` statements, `forge` and `moduledir` stay where they are
+    ///
+    /// Comment lines directly above a module and a comment behind it move with the module.
+    /// Comments separated from a module by a blank line stay in place.
+    pub fn sort_modules(&mut self, order: SortOrder) -> Result<(), PuppetfileError> {
+        let puppetfile = try!(self.to_puppetfile());
+        let slots: Vec<usize> = self.nodes
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|&(_, node)| node.module().is_some())
+                                    .map(|(i, _)| i)
+                                    .collect();
+        let mut sorted: Vec<(usize, &Module)> = slots.iter()
+                                                     .cloned()
+                                                     .zip(puppetfile.modules.iter())
+                                                     .collect();
+        sorted.sort_by_key(|&(_, module)| sort_key(order, module));
+
+        let mut source = String::new();
+        let mut modules = sorted.iter();
+        for node in self.nodes.iter() {
+            if let NodeKind::Module(..) = node.kind {
+                let moved = &self.nodes[modules.next().unwrap().0];
+                source.push_str(&node.leading.text[..attached_comments(&node.leading.text)]);
+                source.push_str(&moved.leading.text[attached_comments(&moved.leading.text)..]);
+                source.push_str(&moved.text);
+                source.push_str(&moved.trailing.text);
+            } else {
+                source.push_str(&node.to_string());
+            }
+        }
+        source.push_str(&self.trailing.text);
+        self.reparse(&source)
+    }
+
+    fn module_index(&self, name: &str) -> Result<usize, PuppetfileError> {
+        self.nodes
+            .iter()
+            .position(|node| {
+                node.module().map(|module| same_module(&module.name.value, name)).unwrap_or(false)
+            })
+            .ok_or_else(|| {
+                From::from((UnknownModule(name.to_string()),
+                            format!("no module '{}' in the Puppetfile", name)))
+            })
+    }
+
+    fn replace_text(&mut self, index: usize, text: String) -> Result<(), PuppetfileError> {
+        let mut source = String::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i == index {
+                source.push_str(&node.leading.text);
+                source.push_str(&text);
+                source.push_str(&node.trailing.text);
+            } else {
+                source.push_str(&node.to_string());
+            }
+        }
+        source.push_str(&self.trailing.text);
+        self.reparse(&source)
+    }
+
+    /// Like `replace_text`, but keeps the tree unchanged if the named module ends up with
+    /// contradicting options
+    fn replace_checked(&mut self,
+                       index: usize,
+                       text: String,
+                       name: &str)
+                       -> Result<(), PuppetfileError> {
+        let mut edited = self.clone();
+        try!(edited.replace_text(index, text));
+        for (module, location) in try!(edited.modules()).iter().zip(edited.module_locations()) {
+            if same_module(&module.name, name) {
+                try!(module.source_at(&location));
+            }
+        }
+        *self = edited;
+        Ok(())
+    }
+
+    /// Replaces the tree if `source` is a valid Puppetfile, keeps it unchanged otherwise
+    fn reparse(&mut self, source: &str) -> Result<(), PuppetfileError> {
+        let tree = try!(SyntaxTree::parse(source));
+        try!(tree.to_puppetfile());
+        *self = tree;
+        Ok(())
+    }
+}
+
+impl Node {
+    /// Returns the module if the node is a `mod` statement
+    pub fn module(&self) -> Option<&ModuleNode> {
+        match self.kind {
+            NodeKind::Module(ref module) => Some(module),
+            NodeKind::Forge(..) | NodeKind::Moduledir(..) => None,
+        }
+    }
+
+    /// Returns the text of the statement with `span` replaced
+    fn replace(&self, span: Span, with: &str) -> String {
+        let start = span.start - self.span.start;
+        let end = span.end - self.span.start;
+        format!("{}{}{}", &self.text[..start], with, &self.text[end..])
+    }
+
+    /// The separator for a new option, continuing the layout of the existing options
+    fn option_separator(&self, module: &ModuleNode) -> String {
+        let last = match module.options.last() {
+            Some(last) => last,
+            None => return ",\n  ".to_string(),
+        };
+        let before = &self.text[..last.span.start - self.span.start];
+        match before.rfind('\n') {
+            Some(line_start) => {
+                let line = &before[line_start + 1..];
+                let indent = line.len() - line.trim_left_matches(|c| c == ' ' || c == '\t').len();
+                format!(",\n{}", &line[..indent])
+            }
+            None => ", ".to_string(),
+        }
+    }
+}
+
+/// Removes one line break from the start of `text`
+fn strip_line_break(text: &str) -> &str {
+    text.strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .or_else(|| text.strip_prefix('\r'))
+        .unwrap_or(text)
+}
+
+/// Returns the offset of the comment lines directly in front of the statement
+fn attached_comments(trivia: &str) -> usize {
+    let mut start = trivia.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    while start > 0 {
+        let line_start = trivia[..start - 1].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        if !trivia[line_start..start].trim().starts_with('#') {
+            break;
+        }
+        start = line_start;
+    }
+    start
+}
+
+/// Quotes and escapes a string with the given quote character so that it parses back to `value`
+pub fn quote(value: &str, quote: char) -> String {
+    let mut quoted = quote.to_string();
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\u{2028}' => quoted.push_str("\\u2028"),
+            '\u{2029}' => quoted.push_str("\\u2029"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+fn value_text(value: &Value, quote_char: char) -> String {
+    match *value {
+        Value::String(ref s) => quote(s, quote_char),
+        Value::ControlBranch | Value::Symbol(..) | Value::Boolean(..) => value.to_string(),
+    }
+}
@@ -0,0 +1,511 @@
+//! A client for the v3 API of the Puppet Forge
+
+use std::cmp;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use rustc_serialize::{json, Decodable, Decoder};
+use semver::{self, VersionReq};
+use url::form_urlencoded;
+
+use super::{Module, PuppetfileError, DEFAULT_FORGE};
+use ErrorKind::*;
+use auth::ForgeAuth;
+use name::ModuleName;
+use retry::RetryPolicy;
+use transport::{Transport, HyperTransport, Request, Response, DEFAULT_TIMEOUT};
+
+/// The number of releases requested per page
+const PAGE_SIZE: usize = 100;
+
+/// The number of modules suggested for a module that was not found
+const SUGGESTIONS: usize = 3;
+
+/// Implements `Decodable` for a struct from the JSON fields of the same names
+macro_rules! decodable {
+    ($name:ident { $($field:ident),* }) => {
+        impl Decodable for $name {
+            fn decode<D: Decoder>(d: &mut D) -> Result<$name, D::Error> {
+                d.read_struct(stringify!($name), 0, |d| {
+                    Ok($name {
+                        $($field: try!(d.read_struct_field(stringify!($field),
+                                                            0,
+                                                            Decodable::decode)),)*
+                    })
+                })
+            }
+        }
+    }
+}
+
+/// A module published on the forge, from `/v3/modules/{slug}`
+#[derive(PartialEq, Clone, Debug)]
+pub struct ForgeModule {
+    /// The slug of the module, e.g. `puppetlabs-stdlib`
+    pub slug: String,
+    /// The name of the module without the owner
+    pub name: String,
+    /// The owner of the module
+    pub owner: Owner,
+    /// The most recent release
+    pub current_release: Release,
+    /// All releases, newest first
+    pub releases: Vec<ReleaseSummary>,
+    /// When the module was deprecated
+    pub deprecated_at: Option<String>,
+    /// Why the module was deprecated
+    pub deprecated_for: Option<String>,
+    /// The module that replaces this one
+    pub superseded_by: Option<ModuleRef>,
+}
+decodable!(ForgeModule {
+    slug, name, owner, current_release, releases, deprecated_at, deprecated_for,
+    superseded_by
+});
+
+impl ForgeModule {
+    /// Whether the module is deprecated
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated_at.is_some()
+    }
+}
+
+/// The owner of a module on the forge
+#[derive(PartialEq, Clone, Debug)]
+pub struct Owner {
+    /// The slug of the owner, e.g. `puppetlabs`
+    pub slug: String,
+    /// The user name of the owner
+    pub username: String,
+}
+decodable!(Owner { slug, username });
+
+/// A reference to another module on the forge
+#[derive(PartialEq, Clone, Debug)]
+pub struct ModuleRef {
+    /// The slug of the module, e.g. `puppetlabs-stdlib`
+    pub slug: String,
+}
+decodable!(ModuleRef { slug });
+
+/// A release as listed in a `ForgeModule`
+#[derive(PartialEq, Clone, Debug)]
+pub struct ReleaseSummary {
+    /// The slug of the release, e.g. `puppetlabs-stdlib-4.10.0`
+    pub slug: String,
+    /// The version as published
+    pub version: String,
+    /// The path of the release tarball on the forge
+    pub file_uri: String,
+    /// When the release was deleted
+    pub deleted_at: Option<String>,
+}
+decodable!(ReleaseSummary { slug, version, file_uri, deleted_at });
+
+/// A release of a module, from `/v3/releases`
+#[derive(PartialEq, Clone, Debug)]
+pub struct Release {
+    /// The slug of the release, e.g. `puppetlabs-stdlib-4.10.0`
+    pub slug: String,
+    /// The version as published
+    pub version: String,
+    /// The module of the release
+    pub module: ModuleRef,
+    /// The `metadata.json` of the release
+    pub metadata: Metadata,
+    /// The path of the release tarball on the forge
+    pub file_uri: String,
+    /// The size of the release tarball in bytes
+    pub file_size: u64,
+    /// The MD5 checksum of the release tarball
+    pub file_md5: String,
+    /// The SHA-256 checksum of the release tarball, missing for old releases
+    pub file_sha256: Option<String>,
+    /// When the release was published
+    pub created_at: String,
+    /// When the release was deleted
+    pub deleted_at: Option<String>,
+}
+decodable!(Release {
+    slug, version, module, metadata, file_uri, file_size, file_md5, file_sha256,
+    created_at, deleted_at
+});
+
+impl Release {
+    /// Parses the version of the release
+    pub fn version(&self) -> Result<semver::Version, PuppetfileError> {
+        Ok(try!(semver::Version::parse(&self.version)))
+    }
+}
+
+/// The `metadata.json` of a release
+#[derive(PartialEq, Clone, Debug)]
+pub struct Metadata {
+    /// The name of the module, e.g. `puppetlabs-stdlib`
+    pub name: String,
+    /// The version of the release
+    pub version: String,
+    /// The author of the module
+    pub author: Option<String>,
+    /// A one-line description of the module
+    pub summary: Option<String>,
+    /// The license of the module
+    pub license: Option<String>,
+    /// The URL of the source code
+    pub source: Option<String>,
+    /// The URL of the project page
+    pub project_page: Option<String>,
+    /// The URL of the issue tracker
+    pub issues_url: Option<String>,
+    /// The modules this release depends on
+    pub dependencies: Option<Vec<Dependency>>,
+}
+decodable!(Metadata {
+    name, version, author, summary, license, source, project_page, issues_url,
+    dependencies
+});
+
+/// A dependency declared in `metadata.json`
+#[derive(PartialEq, Clone, Debug)]
+pub struct Dependency {
+    /// The name of the module, e.g. `puppetlabs/stdlib`
+    pub name: String,
+    /// The versions that satisfy the dependency, e.g. `>= 4.0.0 < 5.0.0`
+    pub version_requirement: Option<String>,
+}
+decodable!(Dependency { name, version_requirement });
+
+/// Which releases `ForgeClient::find_releases` keeps
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct ReleaseFilter {
+    /// Keep only releases matching the requirement
+    pub requirement: Option<VersionReq>,
+    /// Keep pre-releases like `2.0.0-rc1`
+    pub pre_releases: bool,
+    /// Keep deleted releases
+    pub deleted: bool,
+}
+
+impl ReleaseFilter {
+    /// Keeps the releases matching the version of the module, all releases if it has none
+    pub fn for_module(module: &Module) -> ReleaseFilter {
+        ReleaseFilter { requirement: module.version().cloned(), ..ReleaseFilter::default() }
+    }
+
+    /// Whether the release with the version passes the filter
+    pub fn matches(&self, release: &Release, version: &semver::Version) -> bool {
+        (self.deleted || release.deleted_at.is_none()) &&
+        (self.pre_releases || version.pre.is_empty()) &&
+        self.requirement.as_ref().map_or(true, |requirement| requirement.matches(version))
+    }
+}
+
+struct ReleasePage {
+    pagination: Pagination,
+    results: Vec<Release>,
+}
+decodable!(ReleasePage { pagination, results });
+
+struct ModulePage {
+    results: Vec<ModuleRef>,
+}
+decodable!(ModulePage { results });
+
+struct Pagination {
+    next: Option<String>,
+}
+decodable!(Pagination { next });
+
+/// Fetches modules and releases from the v3 API of a forge
+pub struct ForgeClient<T = HyperTransport> {
+    url: String,
+    transport: T,
+    auth: ForgeAuth,
+    retry: RetryPolicy,
+}
+
+impl ForgeClient<HyperTransport> {
+    /// Creates a client for the forge at the given URL, e.g. `https://forgeapi.puppetlabs.com`
+    ///
+    /// Reads and writes time out after `DEFAULT_TIMEOUT` seconds, so a stalled forge fails
+    /// the request and is retried instead of hanging forever.
+    pub fn new(forge_url: &str) -> ForgeClient<HyperTransport> {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT);
+        ForgeClient::with_transport(forge_url, HyperTransport::with_timeout(timeout))
+    }
+}
+
+impl<T: Transport> ForgeClient<T> {
+    /// Creates a client for the forge at the given URL sending requests through the transport
+    pub fn with_transport(forge_url: &str, transport: T) -> ForgeClient<T> {
+        ForgeClient {
+            url: forge_url.trim_right_matches('/').to_string(),
+            transport: transport,
+            auth: ForgeAuth::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets when failed requests are retried
+    pub fn with_retry(mut self, retry: RetryPolicy) -> ForgeClient<T> {
+        self.retry = retry;
+        self
+    }
+
+    /// Authenticates all requests, e.g. with `ForgeCredentials::get`
+    pub fn with_auth(mut self, auth: ForgeAuth) -> ForgeClient<T> {
+        self.auth = auth;
+        self
+    }
+
+    /// The transport requests are sent through
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The URL of the forge without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The URL of the module on the forge API
+    pub fn module_url(&self, name: &ModuleName) -> Result<String, PuppetfileError> {
+        Ok(format!("{}/v3/modules/{}", self.url, try!(forge_slug(name))))
+    }
+
+    /// Fetches the module with its current release and deprecation status
+    ///
+    /// Fails with `ModuleNotFound` and similarly named modules if the forge does not know it.
+    pub fn module(&self, name: &ModuleName) -> Result<ForgeModule, PuppetfileError> {
+        let url = try!(self.module_url(name));
+        match self.get(&url) {
+            Err(PuppetfileError { kind: NotFound { .. }, .. }) => {
+                let suggestions = self.suggestions(name);
+                let mut desc = format!("module '{}' was not found on {}", name, self.url);
+                if !suggestions.is_empty() {
+                    desc.push_str(&format!(", did you mean {}?", suggestions.join(", ")));
+                }
+                Err(From::from((ModuleNotFound {
+                                    module: name.to_string(),
+                                    forge: self.url.clone(),
+                                    suggestions: suggestions,
+                                },
+                                desc)))
+            }
+            result => result,
+        }
+    }
+
+    /// The slugs of the modules the forge search finds for the name, closest first
+    ///
+    /// The forge only finds modules containing the name, so if none of them is close, e.g.
+    /// for `puppetlabs-ngnix`, the modules of the owner are searched as well. Empty if the
+    /// searches fail, the lookup that needs suggestions already failed.
+    pub fn suggestions(&self, name: &ModuleName) -> Vec<String> {
+        let slug = forge_slug(name).unwrap_or(name.name.clone()).to_lowercase();
+        let query = format!("query={}&limit=20", encode(&name.name));
+        let suggestions = self.search(&query, &slug, |slug| slug.to_string());
+        match name.owner {
+            Some(ref owner) if suggestions.is_empty() => {
+                // all modules share the owner, only their names tell them apart
+                let query = format!("owner={}&limit={}", encode(owner), PAGE_SIZE);
+                let prefix = format!("{}-", owner.to_lowercase());
+                self.search(&query,
+                            &name.name.to_lowercase(),
+                            |slug| slug.strip_prefix(&prefix[..]).unwrap_or(slug).to_string())
+            }
+            _ => suggestions,
+        }
+    }
+
+    /// The slugs of the modules the search finds whose key is close to the target,
+    /// closest first
+    fn search<F>(&self, query: &str, target: &str, key: F) -> Vec<String>
+        where F: Fn(&str) -> String
+    {
+        let page: ModulePage = match self.get(&format!("{}/v3/modules?{}", self.url, query)) {
+            Ok(page) => page,
+            Err(..) => return vec![],
+        };
+        let max_distance = cmp::max(2, target.len() / 3);
+        let mut suggestions: Vec<(usize, String)> =
+            page.results
+                .into_iter()
+                .map(|module| (distance(target, &key(&module.slug.to_lowercase())), module.slug))
+                .filter(|&(distance, _)| distance <= max_distance)
+                .collect();
+        suggestions.sort();
+        suggestions.into_iter().take(SUGGESTIONS).map(|(_, slug)| slug).collect()
+    }
+
+    /// Fetches the most recent release of the module
+    pub fn current_release(&self, name: &ModuleName) -> Result<Release, PuppetfileError> {
+        Ok(try!(self.module(name)).current_release)
+    }
+
+    /// Fetches all releases of the module that are not deleted, newest first
+    pub fn releases(&self, name: &ModuleName) -> Result<Vec<Release>, PuppetfileError> {
+        let url = try!(self.releases_url(name));
+        self.get_pages(url)
+    }
+
+    /// Fetches the releases of the module passing the filter, sorted by version, newest first
+    ///
+    /// Releases whose version does not parse are left out.
+    pub fn find_releases(&self,
+                         name: &ModuleName,
+                         filter: &ReleaseFilter)
+                         -> Result<Vec<Release>, PuppetfileError> {
+        let mut url = try!(self.releases_url(name));
+        if filter.deleted {
+            url.push_str("&show_deleted=true");
+        }
+        let mut releases = vec![];
+        for release in try!(self.get_pages(url)) {
+            // releases with a version that is not semver can not match any filter
+            let version = match release.version() {
+                Ok(version) => version,
+                Err(..) => continue,
+            };
+            if filter.matches(&release, &version) {
+                releases.push((version, release));
+            }
+        }
+        releases.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(releases.into_iter().map(|(_, release)| release).collect())
+    }
+
+    fn releases_url(&self, name: &ModuleName) -> Result<String, PuppetfileError> {
+        Ok(format!("{}/v3/releases?module={}&limit={}",
+                   self.url,
+                   try!(forge_slug(name)),
+                   PAGE_SIZE))
+    }
+
+    fn get_pages(&self, mut url: String) -> Result<Vec<Release>, PuppetfileError> {
+        let mut releases = vec![];
+        loop {
+            let page: ReleasePage = try!(self.get(&url));
+            releases.extend(page.results);
+            match page.pagination.next {
+                Some(next) => url = format!("{}{}", self.url, next),
+                None => return Ok(releases),
+            }
+        }
+    }
+
+    fn get<D: Decodable>(&self, url: &str) -> Result<D, PuppetfileError> {
+        let request = self.auth.apply(Request::get(url));
+        let mut attempt = 0;
+        loop {
+            let result = self.transport.send(&request);
+            let retry_after = match result {
+                Ok(ref response) if response.status == 429 || response.status >= 500 => {
+                    response.header("Retry-After").and_then(|seconds| seconds.trim().parse().ok())
+                }
+                Err(ref err) if is_connection_error(err) => None,
+                _ => return decode(url, try!(result)),
+            };
+            match self.retry.delay(attempt, retry_after) {
+                Some(delay) => thread::sleep(delay),
+                None => return decode(url, try!(result)),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+/// The API of the forge, the Puppet Forge website serves its API on a separate host
+pub fn api_url(forge_url: &str) -> &str {
+    match forge_url.trim_right_matches('/') {
+        "https://forge.puppetlabs.com" | "http://forge.puppetlabs.com" => DEFAULT_FORGE,
+        _ => forge_url,
+    }
+}
+
+/// Whether the request failed talking to the forge, e.g. a refused connection or a timeout,
+/// as opposed to a local error like an unwritable cache
+fn is_connection_error(err: &PuppetfileError) -> bool {
+    match err.kind {
+        HttpError(..) => true,
+        IoError(ref err) => {
+            match err.kind() {
+                io::ErrorKind::ConnectionRefused |
+                io::ErrorKind::ConnectionReset |
+                io::ErrorKind::ConnectionAborted |
+                io::ErrorKind::NotConnected |
+                io::ErrorKind::BrokenPipe |
+                io::ErrorKind::TimedOut |
+                io::ErrorKind::WouldBlock |
+                io::ErrorKind::Interrupted |
+                io::ErrorKind::UnexpectedEof => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn decode<D: Decodable>(url: &str, response: Response) -> Result<D, PuppetfileError> {
+    let kind = match response.status {
+        200...299 => return Ok(try!(json::decode(&response.body))),
+        404 => NotFound { url: url.to_string() },
+        429 => {
+            RateLimited {
+                url: url.to_string(),
+                retry_after: response.header("Retry-After")
+                                     .and_then(|seconds| seconds.trim().parse().ok()),
+            }
+        }
+        500...599 => {
+            ServerError {
+                url: url.to_string(),
+                status: response.status,
+            }
+        }
+        status => {
+            HttpStatus {
+                url: url.to_string(),
+                status: status,
+            }
+        }
+    };
+    let desc = match kind {
+        NotFound { .. } => format!("{} was not found on the forge", url),
+        RateLimited { .. } => format!("the forge rate limited the request for {}", url),
+        _ => format!("the forge answered {} for {}", response.status, url),
+    };
+    Err(From::from((kind, desc)))
+}
+
+/// Percent-encodes a query parameter value
+fn encode(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// The forge only knows modules with an owner
+fn forge_slug(name: &ModuleName) -> Result<String, PuppetfileError> {
+    match name.owner {
+        Some(..) => Ok(name.slug()),
+        None => {
+            Err(From::from((UrlBuilding,
+                            format!("module '{}' has no owner, it is not on the forge", name))))
+        }
+    }
+}
+
+/// The Levenshtein distance between two slugs
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == b { 0 } else { 1 };
+            current.push(cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
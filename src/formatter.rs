@@ -0,0 +1,279 @@
+//! Canonical formatting of a Puppetfile
+//!
+//! The formatter works on the syntax tree, so comments in front of, behind and inside of
+//! statements are kept.
+
+use std::cmp;
+
+use super::HashStyle;
+use edit::quote;
+use syntax::{SyntaxTree, Node, NodeKind, ModuleNode, OptionKind, ValueNode, Str};
+
+/// How a formatted Puppetfile looks
+#[derive(PartialEq, Clone, Debug)]
+pub struct FormatStyle {
+    /// The preferred quote character, the other one is used if it saves escaping
+    pub quote: char,
+    /// The syntax options are converted to, `None` keeps the syntax as written
+    pub hash_style: Option<HashStyle>,
+    /// Pad the keys of a module so that `=>` and the values line up
+    pub align: bool,
+    /// Number of spaces in front of options on their own line
+    pub indent: usize,
+    /// Number of blank lines between two modules
+    pub blank_lines: usize,
+    /// Move `forge` and `moduledir` in front of all modules
+    pub directives_first: bool,
+}
+
+impl Default for FormatStyle {
+    fn default() -> FormatStyle {
+        FormatStyle {
+            quote: '\'',
+            hash_style: None,
+            align: true,
+            indent: 2,
+            blank_lines: 1,
+            directives_first: true,
+        }
+    }
+}
+
+impl SyntaxTree {
+    /// Formats the Puppetfile in the given style
+    pub fn format(&self, style: &FormatStyle) -> String {
+        let mut nodes: Vec<&Node> = self.nodes.iter().collect();
+        if style.directives_first {
+            nodes.sort_by_key(|node| {
+                match node.kind {
+                    NodeKind::Forge(..) => 0,
+                    NodeKind::Moduledir(..) => 1,
+                    NodeKind::Module(..) => 2,
+                }
+            });
+        }
+
+        // the comments in front of the first statement stay at the top of the file
+        let first = self.nodes.first();
+        let mut out = first.map(|node| comment_blocks(&node.leading.text)).unwrap_or_default();
+        let mut previous: Option<&Node> = None;
+        for node in nodes {
+            if let Some(previous) = previous {
+                let blank_lines = match (&previous.kind, &node.kind) {
+                    (&NodeKind::Module(..), &NodeKind::Module(..)) => style.blank_lines,
+                    (&NodeKind::Module(..), _) | (_, &NodeKind::Module(..)) => 1,
+                    _ => 0,
+                };
+                out.push_str(&newlines(blank_lines + 1));
+            }
+            if Some(node) != first {
+                out.push_str(&comment_blocks(&node.leading.text));
+            }
+            out.push_str(&match node.kind {
+                NodeKind::Forge(ref url) => format!("forge {}", style.quote(url)),
+                NodeKind::Moduledir(ref path) => format!("moduledir {}", style.quote(path)),
+                NodeKind::Module(ref module) => style.module(node, module),
+            });
+            for comment in node.trailing.comments() {
+                out.push(' ');
+                out.push_str(comment);
+            }
+            previous = Some(node);
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        let trailing = comment_blocks(&self.trailing.text);
+        if !trailing.is_empty() {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(trailing.trim_right());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// An option or the name of a module together with the comments around it
+struct Element {
+    key: Option<String>,
+    text: String,
+    leading: Vec<String>,
+    trailing: Option<String>,
+}
+
+impl FormatStyle {
+    fn quote(&self, s: &Str) -> String {
+        let other = if self.quote == '"' { '\'' } else { '"' };
+        if s.value.contains(self.quote) && !s.value.contains(other) {
+            quote(&s.value, other)
+        } else {
+            quote(&s.value, self.quote)
+        }
+    }
+
+    fn module(&self, node: &Node, module: &ModuleNode) -> String {
+        let mut elements = vec![Element {
+                                    key: None,
+                                    text: format!("mod {}", self.quote(&module.name)),
+                                    leading: vec![],
+                                    trailing: None,
+                                }];
+        let mut end = module.name.span.end;
+        for option in module.options.iter() {
+            let gap = &node.text[end - node.span.start..option.span.start - node.span.start];
+            let mut leading = vec![];
+            for (i, line) in gap.split('\n').enumerate() {
+                if let Some(start) = line.find('#') {
+                    let comment = line[start..].trim_right().to_string();
+                    if i == 0 {
+                        elements.last_mut().unwrap().trailing = Some(comment);
+                    } else {
+                        leading.push(comment);
+                    }
+                }
+            }
+            end = option.span.end;
+
+            let (key, text) = match option.kind {
+                OptionKind::Version(ref version) => (None, self.quote(version)),
+                OptionKind::Latest => (None, ":latest".to_string()),
+                OptionKind::Pair { ref key, ref value, style } => {
+                    let value = match *value {
+                        ValueNode::String(ref s) => self.quote(s),
+                        ValueNode::Symbol(ref symbol) => format!(":{}", symbol.name),
+                        ValueNode::Boolean { value, .. } => value.to_string(),
+                    };
+                    match self.hash_style.unwrap_or(style) {
+                        HashStyle::HashRocket => (Some(format!(":{}", key.name)), value),
+                        HashStyle::Ruby19 => (Some(format!("{}:", key.name)), value),
+                    }
+                }
+            };
+            elements.push(Element {
+                key: key,
+                text: text,
+                leading: leading,
+                trailing: None,
+            });
+        }
+
+        // versions stay on the line of the name unless comments are in the way
+        let mut head = 1;
+        while head < elements.len() && elements[head].key.is_none() &&
+              elements[head].leading.is_empty() &&
+              elements[head - 1].trailing.is_none() {
+            head += 1;
+        }
+        let width = elements.iter()
+                            .filter_map(|element| element.key.as_ref())
+                            .map(|key| key.chars().count())
+                            .max()
+                            .unwrap_or(0);
+        let indent = spaces(self.indent);
+
+        let mut out = elements[..head]
+                          .iter()
+                          .map(|element| element.text.clone())
+                          .collect::<Vec<_>>()
+                          .join(", ");
+        for (i, element) in elements.iter().enumerate() {
+            if i >= head {
+                out.push('\n');
+                for comment in element.leading.iter() {
+                    out.push_str(&format!("{}{}\n", indent, comment));
+                }
+                out.push_str(&indent);
+                out.push_str(&match element.key {
+                    Some(ref key) if key.starts_with(':') => {
+                        let padding = if self.align { width - key.chars().count() } else { 0 };
+                        format!("{}{} => {}", key, spaces(padding), element.text)
+                    }
+                    Some(ref key) => {
+                        let padding = if self.align { width - key.chars().count() } else { 0 };
+                        format!("{}{} {}", key, spaces(padding), element.text)
+                    }
+                    None => element.text.clone(),
+                });
+            }
+            if i + 1 >= head && i + 1 < elements.len() {
+                out.push(',');
+            }
+            if i + 1 >= head {
+                if let Some(ref comment) = element.trailing {
+                    out.push(' ');
+                    out.push_str(comment);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Returns the comments of trivia with at most one blank line between blocks of comments
+fn comment_blocks(trivia: &str) -> String {
+    let lines: Vec<&str> = trivia.split('\n').collect();
+    let mut out = String::new();
+    let mut blank = false;
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            if blank && !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(line);
+            out.push('\n');
+            blank = false;
+        } else if i > 0 && i + 1 < lines.len() {
+            blank = true;
+        }
+    }
+    if blank && !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Returns the lines of `formatted` that differ from `original` as a unified style diff
+pub fn line_diff(original: &str, formatted: &str) -> String {
+    let a: Vec<&str> = original.lines().collect();
+    let b: Vec<&str> = formatted.lines().collect();
+
+    // lengths of the longest common subsequences of all suffixes
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("{:>4} -{}\n", i + 1, a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("{:>4} +{}\n", j + 1, b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn spaces(len: usize) -> String {
+    " ".repeat(len)
+}
+
+fn newlines(len: usize) -> String {
+    "\n".repeat(len)
+}
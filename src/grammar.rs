@@ -3,8 +3,7 @@
 use std::char;
 use std::str;
 use super::*;
-use semver::VersionReq;
-use semver;
+use syntax::*;
 use self::RuleResult::{Matched, Failed};
 fn escape_default(s: &str) -> String {
     s.chars().flat_map(|c| c.escape_default()).collect()
@@ -119,7 +118,7 @@ impl <'input> ParseState<'input> {
     }
 }
 fn parse_parse<'input>(input: &'input str, state: &mut ParseState<'input>,
-                       pos: usize) -> RuleResult<Puppetfile> {
+                       pos: usize) -> RuleResult<Vec<(Span, NodeKind)>> {
     {
         let start_pos = pos;
         {
@@ -127,38 +126,65 @@ fn parse_parse<'input>(input: &'input str, state: &mut ParseState<'input>,
             match seq_res {
                 Matched(pos, _) => {
                     {
-                        let seq_res = parse_forge(input, state, pos);
-                        match seq_res {
-                            Matched(pos, forge) => {
-                                {
-                                    let seq_res = parse___(input, state, pos);
-                                    match seq_res {
-                                        Matched(pos, _) => {
+                        let seq_res =
+                            {
+                                let mut repeat_pos = pos;
+                                let mut repeat_value = vec!();
+                                loop  {
+                                    let pos = repeat_pos;
+                                    let step_res =
+                                        {
+                                            let start_pos = pos;
                                             {
                                                 let seq_res =
-                                                    parse_modules(input,
-                                                                  state, pos);
+                                                    parse_statement(input,
+                                                                    state,
+                                                                    pos);
                                                 match seq_res {
-                                                    Matched(pos, modules) => {
+                                                    Matched(pos, s) => {
                                                         {
-                                                            let match_str =
-                                                                &input[start_pos..pos];
-                                                            Matched(pos,
+                                                            let seq_res =
+                                                                parse___(input,
+                                                                         state,
+                                                                         pos);
+                                                            match seq_res {
+                                                                Matched(pos,
+                                                                        _) =>
+                                                                {
                                                                     {
-                                                                        Puppetfile{forge:
-                                                                                       forge,
-                                                                                   modules:
-                                                                                       modules,}
-                                                                    })
+                                                                        let match_str =
+                                                                            &input[start_pos..pos];
+                                                                        Matched(pos,
+                                                                                {
+                                                                                    s
+                                                                                })
+                                                                    }
+                                                                }
+                                                                Failed =>
+                                                                Failed,
+                                                            }
                                                         }
                                                     }
                                                     Failed => Failed,
                                                 }
                                             }
+                                        };
+                                    match step_res {
+                                        Matched(newpos, value) => {
+                                            repeat_pos = newpos;
+                                            repeat_value.push(value);
                                         }
-                                        Failed => Failed,
+                                        Failed => { break ; }
                                     }
                                 }
+                                Matched(repeat_pos, repeat_value)
+                            };
+                        match seq_res {
+                            Matched(pos, statements) => {
+                                {
+                                    let match_str = &input[start_pos..pos];
+                                    Matched(pos, { statements })
+                                }
                             }
                             Failed => Failed,
                         }
@@ -169,8 +195,40 @@ fn parse_parse<'input>(input: &'input str, state: &mut ParseState<'input>,
         }
     }
 }
+fn parse_statement<'input>(input: &'input str, state: &mut ParseState<'input>,
+                           pos: usize) -> RuleResult<(Span, NodeKind)> {
+    {
+        let start_pos = pos;
+        {
+            let seq_res =
+                {
+                    let choice_res = parse_forge(input, state, pos);
+                    match choice_res {
+                        Matched(pos, value) => Matched(pos, value),
+                        Failed => {
+                            let choice_res =
+                                parse_moduledir(input, state, pos);
+                            match choice_res {
+                                Matched(pos, value) => Matched(pos, value),
+                                Failed => parse_module(input, state, pos),
+                            }
+                        }
+                    }
+                };
+            match seq_res {
+                Matched(pos, kind) => {
+                    {
+                        let match_str = &input[start_pos..pos];
+                        Matched(pos, { (Span::new(start_pos, pos), kind) })
+                    }
+                }
+                Failed => Failed,
+            }
+        }
+    }
+}
 fn parse_forge<'input>(input: &'input str, state: &mut ParseState<'input>,
-                       pos: usize) -> RuleResult<String> {
+                       pos: usize) -> RuleResult<NodeKind> {
     {
         let start_pos = pos;
         {
@@ -189,7 +247,10 @@ fn parse_forge<'input>(input: &'input str, state: &mut ParseState<'input>,
                                             {
                                                 let match_str =
                                                     &input[start_pos..pos];
-                                                Matched(pos, { url })
+                                                Matched(pos,
+                                                        {
+                                                            NodeKind::Forge(url)
+                                                        })
                                             }
                                         }
                                         Failed => Failed,
@@ -205,27 +266,47 @@ fn parse_forge<'input>(input: &'input str, state: &mut ParseState<'input>,
         }
     }
 }
-fn parse_modules<'input>(input: &'input str, state: &mut ParseState<'input>,
-                         pos: usize) -> RuleResult<Vec<Module>> {
+fn parse_moduledir<'input>(input: &'input str, state: &mut ParseState<'input>,
+                           pos: usize) -> RuleResult<NodeKind> {
     {
-        let mut repeat_pos = pos;
-        let mut repeat_value = vec!();
-        loop  {
-            let pos = repeat_pos;
-            let step_res = parse_module(input, state, pos);
-            match step_res {
-                Matched(newpos, value) => {
-                    repeat_pos = newpos;
-                    repeat_value.push(value);
+        let start_pos = pos;
+        {
+            let seq_res = slice_eq(input, state, pos, "moduledir");
+            match seq_res {
+                Matched(pos, _) => {
+                    {
+                        let seq_res = parse___(input, state, pos);
+                        match seq_res {
+                            Matched(pos, _) => {
+                                {
+                                    let seq_res =
+                                        parse_string(input, state, pos);
+                                    match seq_res {
+                                        Matched(pos, path) => {
+                                            {
+                                                let match_str =
+                                                    &input[start_pos..pos];
+                                                Matched(pos,
+                                                        {
+                                                            NodeKind::Moduledir(path)
+                                                        })
+                                            }
+                                        }
+                                        Failed => Failed,
+                                    }
+                                }
+                            }
+                            Failed => Failed,
+                        }
+                    }
                 }
-                Failed => { break ; }
+                Failed => Failed,
             }
         }
-        Matched(repeat_pos, repeat_value)
     }
 }
 fn parse_module<'input>(input: &'input str, state: &mut ParseState<'input>,
-                        pos: usize) -> RuleResult<Module> {
+                        pos: usize) -> RuleResult<NodeKind> {
     {
         let start_pos = pos;
         {
@@ -243,65 +324,35 @@ fn parse_module<'input>(input: &'input str, state: &mut ParseState<'input>,
                                         Matched(pos, name) => {
                                             {
                                                 let seq_res =
-                                                    parse___(input, state,
-                                                             pos);
-                                                match seq_res {
-                                                    Matched(pos, _) => {
-                                                        {
-                                                            let seq_res =
-                                                                match {
-                                                                          let seq_res =
-                                                                              slice_eq(input,
-                                                                                       state,
-                                                                                       pos,
-                                                                                       ",");
-                                                                          match seq_res
-                                                                              {
-                                                                              Matched(pos,
-                                                                                      _)
-                                                                              =>
-                                                                              {
-                                                                                  parse___(input,
-                                                                                           state,
-                                                                                           pos)
-                                                                              }
-                                                                              Failed
-                                                                              =>
-                                                                              Failed,
-                                                                          }
-                                                                      } {
-                                                                    Matched(newpos,
-                                                                            value)
-                                                                    => {
-                                                                        Matched(newpos,
-                                                                                Some(value))
-                                                                    }
-                                                                    Failed =>
-                                                                    {
-                                                                        Matched(pos,
-                                                                                None)
-                                                                    }
-                                                                };
-                                                            match seq_res {
-                                                                Matched(pos,
-                                                                        _) =>
+                                                    {
+                                                        let mut repeat_pos =
+                                                            pos;
+                                                        let mut repeat_value =
+                                                            vec!();
+                                                        loop  {
+                                                            let pos =
+                                                                repeat_pos;
+                                                            let step_res =
                                                                 {
+                                                                    let start_pos =
+                                                                        pos;
                                                                     {
                                                                         let seq_res =
-                                                                            parse_module_info(input,
-                                                                                              state,
-                                                                                              pos);
+                                                                            parse___(input,
+                                                                                     state,
+                                                                                     pos);
                                                                         match seq_res
                                                                             {
                                                                             Matched(pos,
-                                                                                    info)
+                                                                                    _)
                                                                             =>
                                                                             {
                                                                                 {
                                                                                     let seq_res =
-                                                                                        parse___(input,
+                                                                                        slice_eq(input,
                                                                                                  state,
-                                                                                                 pos);
+                                                                                                 pos,
+                                                                                                 ",");
                                                                                     match seq_res
                                                                                         {
                                                                                         Matched(pos,
@@ -309,15 +360,46 @@ fn parse_module<'input>(input: &'input str, state: &mut ParseState<'input>,
                                                                                         =>
                                                                                         {
                                                                                             {
-                                                                                                let match_str =
-                                                                                                    &input[start_pos..pos];
-                                                                                                Matched(pos,
+                                                                                                let seq_res =
+                                                                                                    parse___(input,
+                                                                                                             state,
+                                                                                                             pos);
+                                                                                                match seq_res
+                                                                                                    {
+                                                                                                    Matched(pos,
+                                                                                                            _)
+                                                                                                    =>
+                                                                                                    {
                                                                                                         {
-                                                                                                            Module{name:
-                                                                                                                       name,
-                                                                                                                   info:
-                                                                                                                       info,}
-                                                                                                        })
+                                                                                                            let seq_res =
+                                                                                                                parse_option(input,
+                                                                                                                             state,
+                                                                                                                             pos);
+                                                                                                            match seq_res
+                                                                                                                {
+                                                                                                                Matched(pos,
+                                                                                                                        o)
+                                                                                                                =>
+                                                                                                                {
+                                                                                                                    {
+                                                                                                                        let match_str =
+                                                                                                                            &input[start_pos..pos];
+                                                                                                                        Matched(pos,
+                                                                                                                                {
+                                                                                                                                    o
+                                                                                                                                })
+                                                                                                                    }
+                                                                                                                }
+                                                                                                                Failed
+                                                                                                                =>
+                                                                                                                Failed,
+                                                                                                            }
+                                                                                                        }
+                                                                                                    }
+                                                                                                    Failed
+                                                                                                    =>
+                                                                                                    Failed,
+                                                                                                }
                                                                                             }
                                                                                         }
                                                                                         Failed
@@ -331,11 +413,37 @@ fn parse_module<'input>(input: &'input str, state: &mut ParseState<'input>,
                                                                             Failed,
                                                                         }
                                                                     }
+                                                                };
+                                                            match step_res {
+                                                                Matched(newpos,
+                                                                        value)
+                                                                => {
+                                                                    repeat_pos
+                                                                        =
+                                                                        newpos;
+                                                                    repeat_value.push(value);
+                                                                }
+                                                                Failed => {
+                                                                    break ;
                                                                 }
-                                                                Failed =>
-                                                                Failed,
                                                             }
                                                         }
+                                                        Matched(repeat_pos,
+                                                                repeat_value)
+                                                    };
+                                                match seq_res {
+                                                    Matched(pos, options) => {
+                                                        {
+                                                            let match_str =
+                                                                &input[start_pos..pos];
+                                                            Matched(pos,
+                                                                    {
+                                                                        NodeKind::Module(ModuleNode{name:
+                                                                                                        name,
+                                                                                                    options:
+                                                                                                        options,})
+                                                                    })
+                                                        }
                                                     }
                                                     Failed => Failed,
                                                 }
@@ -354,62 +462,36 @@ fn parse_module<'input>(input: &'input str, state: &mut ParseState<'input>,
         }
     }
 }
-fn parse_module_info<'input>(input: &'input str,
-                             state: &mut ParseState<'input>, pos: usize)
- -> RuleResult<Vec<ModuleInfo>> {
+fn parse_option<'input>(input: &'input str, state: &mut ParseState<'input>,
+                        pos: usize) -> RuleResult<OptionNode> {
     {
         let start_pos = pos;
         {
             let seq_res =
                 {
-                    let mut repeat_pos = pos;
-                    let mut repeat_value = vec!();
-                    loop  {
-                        let pos = repeat_pos;
-                        let pos =
-                            if repeat_value.len() > 0 {
-                                let sep_res =
-                                    {
-                                        let seq_res =
-                                            slice_eq(input, state, pos, ",");
-                                        match seq_res {
-                                            Matched(pos, _) => {
-                                                parse___(input, state, pos)
-                                            }
-                                            Failed => Failed,
-                                        }
-                                    };
-                                match sep_res {
-                                    Matched(newpos, _) => { newpos }
-                                    Failed => break ,
-                                }
-                            } else { pos };
-                        let step_res =
-                            {
-                                let choice_res =
-                                    parse_version(input, state, pos);
-                                match choice_res {
-                                    Matched(pos, value) =>
-                                    Matched(pos, value),
-                                    Failed =>
-                                    parse_info_hash(input, state, pos),
-                                }
-                            };
-                        match step_res {
-                            Matched(newpos, value) => {
-                                repeat_pos = newpos;
-                                repeat_value.push(value);
+                    let choice_res = parse_version(input, state, pos);
+                    match choice_res {
+                        Matched(pos, value) => Matched(pos, value),
+                        Failed => {
+                            let choice_res =
+                                parse_info_hash(input, state, pos);
+                            match choice_res {
+                                Matched(pos, value) => Matched(pos, value),
+                                Failed => parse_latest(input, state, pos),
                             }
-                            Failed => { break ; }
                         }
                     }
-                    Matched(repeat_pos, repeat_value)
                 };
             match seq_res {
-                Matched(pos, i) => {
+                Matched(pos, kind) => {
                     {
                         let match_str = &input[start_pos..pos];
-                        Matched(pos, { i })
+                        Matched(pos,
+                                {
+                                    OptionNode{span:
+                                                   Span::new(start_pos, pos),
+                                               kind: kind,}
+                                })
                     }
                 }
                 Failed => Failed,
@@ -418,7 +500,7 @@ fn parse_module_info<'input>(input: &'input str,
     }
 }
 fn parse_version<'input>(input: &'input str, state: &mut ParseState<'input>,
-                         pos: usize) -> RuleResult<ModuleInfo> {
+                         pos: usize) -> RuleResult<OptionKind> {
     {
         let start_pos = pos;
         {
@@ -426,22 +508,57 @@ fn parse_version<'input>(input: &'input str, state: &mut ParseState<'input>,
             match seq_res {
                 Matched(pos, version) => {
                     {
-                        let seq_res = parse___(input, state, pos);
+                        let match_str = &input[start_pos..pos];
+                        Matched(pos, { OptionKind::Version(version) })
+                    }
+                }
+                Failed => Failed,
+            }
+        }
+    }
+}
+fn parse_latest<'input>(input: &'input str, state: &mut ParseState<'input>,
+                        pos: usize) -> RuleResult<OptionKind> {
+    {
+        let start_pos = pos;
+        {
+            let seq_res = slice_eq(input, state, pos, ":latest");
+            match seq_res {
+                Matched(pos, _) => {
+                    {
+                        let seq_res =
+                            {
+                                let assert_res =
+                                    {
+                                        let choice_res =
+                                            parse_letter(input, state, pos);
+                                        match choice_res {
+                                            Matched(pos, value) =>
+                                            Matched(pos, value),
+                                            Failed => {
+                                                let choice_res =
+                                                    parse_digit(input, state,
+                                                                pos);
+                                                match choice_res {
+                                                    Matched(pos, value) =>
+                                                    Matched(pos, value),
+                                                    Failed =>
+                                                    slice_eq(input, state,
+                                                             pos, "_"),
+                                                }
+                                            }
+                                        }
+                                    };
+                                match assert_res {
+                                    Failed => Matched(pos, ()),
+                                    Matched(..) => Failed,
+                                }
+                            };
                         match seq_res {
                             Matched(pos, _) => {
                                 {
                                     let match_str = &input[start_pos..pos];
-                                    Matched(pos,
-                                            {
-                                                if semver::Version::parse(&version).is_ok()
-                                                   {
-                                                    ModuleInfo::Version(VersionReq::parse(&format!("={}"
-                                                                                                   ,
-                                                                                                   version)).unwrap())
-                                                } else {
-                                                    ModuleInfo::Version(VersionReq::parse(&version).unwrap())
-                                                }
-                                            })
+                                    Matched(pos, { OptionKind::Latest })
                                 }
                             }
                             Failed => Failed,
@@ -454,75 +571,275 @@ fn parse_version<'input>(input: &'input str, state: &mut ParseState<'input>,
     }
 }
 fn parse_info_hash<'input>(input: &'input str, state: &mut ParseState<'input>,
-                           pos: usize) -> RuleResult<ModuleInfo> {
+                           pos: usize) -> RuleResult<OptionKind> {
     {
-        let start_pos = pos;
-        {
-            let seq_res = parse_symbol(input, state, pos);
-            match seq_res {
-                Matched(pos, key) => {
-                    {
-                        let seq_res = parse___(input, state, pos);
-                        match seq_res {
-                            Matched(pos, _) => {
-                                {
-                                    let seq_res =
-                                        slice_eq(input, state, pos, "=>");
-                                    match seq_res {
-                                        Matched(pos, _) => {
-                                            {
-                                                let seq_res =
-                                                    parse___(input, state,
-                                                             pos);
-                                                match seq_res {
-                                                    Matched(pos, _) => {
-                                                        {
-                                                            let seq_res =
-                                                                parse_string(input,
-                                                                             state,
-                                                                             pos);
-                                                            match seq_res {
-                                                                Matched(pos,
-                                                                        value)
-                                                                => {
-                                                                    {
-                                                                        let seq_res =
-                                                                            parse___(input,
-                                                                                     state,
-                                                                                     pos);
-                                                                        match seq_res
-                                                                            {
-                                                                            Matched(pos,
-                                                                                    _)
-                                                                            =>
+        let choice_res =
+            {
+                let start_pos = pos;
+                {
+                    let seq_res = parse_symbol(input, state, pos);
+                    match seq_res {
+                        Matched(pos, key) => {
+                            {
+                                let seq_res = parse___(input, state, pos);
+                                match seq_res {
+                                    Matched(pos, _) => {
+                                        {
+                                            let seq_res =
+                                                slice_eq(input, state, pos,
+                                                         "=>");
+                                            match seq_res {
+                                                Matched(pos, _) => {
+                                                    {
+                                                        let seq_res =
+                                                            parse___(input,
+                                                                     state,
+                                                                     pos);
+                                                        match seq_res {
+                                                            Matched(pos, _) =>
+                                                            {
+                                                                {
+                                                                    let seq_res =
+                                                                        parse_value(input,
+                                                                                    state,
+                                                                                    pos);
+                                                                    match seq_res
+                                                                        {
+                                                                        Matched(pos,
+                                                                                value)
+                                                                        => {
                                                                             {
-                                                                                {
-                                                                                    let match_str =
-                                                                                        &input[start_pos..pos];
-                                                                                    Matched(pos,
-                                                                                            {
-                                                                                                ModuleInfo::Info(key,
-                                                                                                                 value)
-                                                                                            })
-                                                                                }
+                                                                                let match_str =
+                                                                                    &input[start_pos..pos];
+                                                                                Matched(pos,
+                                                                                        {
+                                                                                            OptionKind::Pair{key:
+                                                                                                                 key,
+                                                                                                             value:
+                                                                                                                 value,
+                                                                                                             style:
+                                                                                                                 HashStyle::HashRocket,}
+                                                                                        })
                                                                             }
-                                                                            Failed
-                                                                            =>
-                                                                            Failed,
                                                                         }
+                                                                        Failed
+                                                                        =>
+                                                                        Failed,
                                                                     }
                                                                 }
-                                                                Failed =>
-                                                                Failed,
                                                             }
+                                                            Failed => Failed,
                                                         }
                                                     }
-                                                    Failed => Failed,
                                                 }
+                                                Failed => Failed,
                                             }
                                         }
-                                        Failed => Failed,
                                     }
+                                    Failed => Failed,
+                                }
+                            }
+                        }
+                        Failed => Failed,
+                    }
+                }
+            };
+        match choice_res {
+            Matched(pos, value) => Matched(pos, value),
+            Failed => {
+                let start_pos = pos;
+                {
+                    let seq_res = parse_label(input, state, pos);
+                    match seq_res {
+                        Matched(pos, key) => {
+                            {
+                                let seq_res =
+                                    slice_eq(input, state, pos, ":");
+                                match seq_res {
+                                    Matched(pos, _) => {
+                                        {
+                                            let seq_res =
+                                                parse___(input, state, pos);
+                                            match seq_res {
+                                                Matched(pos, _) => {
+                                                    {
+                                                        let seq_res =
+                                                            parse_value(input,
+                                                                        state,
+                                                                        pos);
+                                                        match seq_res {
+                                                            Matched(pos,
+                                                                    value) =>
+                                                            {
+                                                                {
+                                                                    let match_str =
+                                                                        &input[start_pos..pos];
+                                                                    Matched(pos,
+                                                                            {
+                                                                                OptionKind::Pair{key:
+                                                                                                     key,
+                                                                                                 value:
+                                                                                                     value,
+                                                                                                 style:
+                                                                                                     HashStyle::Ruby19,}
+                                                                            })
+                                                                }
+                                                            }
+                                                            Failed => Failed,
+                                                        }
+                                                    }
+                                                }
+                                                Failed => Failed,
+                                            }
+                                        }
+                                    }
+                                    Failed => Failed,
+                                }
+                            }
+                        }
+                        Failed => Failed,
+                    }
+                }
+            }
+        }
+    }
+}
+fn parse_value<'input>(input: &'input str, state: &mut ParseState<'input>,
+                       pos: usize) -> RuleResult<ValueNode> {
+    {
+        let choice_res =
+            {
+                let start_pos = pos;
+                {
+                    let seq_res = parse_string(input, state, pos);
+                    match seq_res {
+                        Matched(pos, s) => {
+                            {
+                                let match_str = &input[start_pos..pos];
+                                Matched(pos, { ValueNode::String(s) })
+                            }
+                        }
+                        Failed => Failed,
+                    }
+                }
+            };
+        match choice_res {
+            Matched(pos, value) => Matched(pos, value),
+            Failed => {
+                let choice_res =
+                    {
+                        let start_pos = pos;
+                        {
+                            let seq_res = parse_symbol(input, state, pos);
+                            match seq_res {
+                                Matched(pos, s) => {
+                                    {
+                                        let match_str =
+                                            &input[start_pos..pos];
+                                        Matched(pos, { ValueNode::Symbol(s) })
+                                    }
+                                }
+                                Failed => Failed,
+                            }
+                        }
+                    };
+                match choice_res {
+                    Matched(pos, value) => Matched(pos, value),
+                    Failed => parse_boolean(input, state, pos),
+                }
+            }
+        }
+    }
+}
+fn parse_boolean<'input>(input: &'input str, state: &mut ParseState<'input>,
+                         pos: usize) -> RuleResult<ValueNode> {
+    {
+        let start_pos = pos;
+        {
+            let seq_res =
+                {
+                    let choice_res =
+                        {
+                            let start_pos = pos;
+                            {
+                                let seq_res =
+                                    slice_eq(input, state, pos, "true");
+                                match seq_res {
+                                    Matched(pos, _) => {
+                                        {
+                                            let match_str =
+                                                &input[start_pos..pos];
+                                            Matched(pos, { true })
+                                        }
+                                    }
+                                    Failed => Failed,
+                                }
+                            }
+                        };
+                    match choice_res {
+                        Matched(pos, value) => Matched(pos, value),
+                        Failed => {
+                            let start_pos = pos;
+                            {
+                                let seq_res =
+                                    slice_eq(input, state, pos, "false");
+                                match seq_res {
+                                    Matched(pos, _) => {
+                                        {
+                                            let match_str =
+                                                &input[start_pos..pos];
+                                            Matched(pos, { false })
+                                        }
+                                    }
+                                    Failed => Failed,
+                                }
+                            }
+                        }
+                    }
+                };
+            match seq_res {
+                Matched(pos, value) => {
+                    {
+                        let seq_res =
+                            {
+                                let assert_res =
+                                    {
+                                        let choice_res =
+                                            parse_letter(input, state, pos);
+                                        match choice_res {
+                                            Matched(pos, value) =>
+                                            Matched(pos, value),
+                                            Failed => {
+                                                let choice_res =
+                                                    parse_digit(input, state,
+                                                                pos);
+                                                match choice_res {
+                                                    Matched(pos, value) =>
+                                                    Matched(pos, value),
+                                                    Failed =>
+                                                    slice_eq(input, state,
+                                                             pos, "_"),
+                                                }
+                                            }
+                                        }
+                                    };
+                                match assert_res {
+                                    Failed => Matched(pos, ()),
+                                    Matched(..) => Failed,
+                                }
+                            };
+                        match seq_res {
+                            Matched(pos, _) => {
+                                {
+                                    let match_str = &input[start_pos..pos];
+                                    Matched(pos,
+                                            {
+                                                ValueNode::Boolean{span:
+                                                                       Span::new(start_pos,
+                                                                                 pos),
+                                                                   value:
+                                                                       value,}
+                                            })
                                 }
                             }
                             Failed => Failed,
@@ -535,7 +852,7 @@ fn parse_info_hash<'input>(input: &'input str, state: &mut ParseState<'input>,
     }
 }
 fn parse_symbol<'input>(input: &'input str, state: &mut ParseState<'input>,
-                        pos: usize) -> RuleResult<String> {
+                        pos: usize) -> RuleResult<Ident> {
     {
         let start_pos = pos;
         {
@@ -543,12 +860,18 @@ fn parse_symbol<'input>(input: &'input str, state: &mut ParseState<'input>,
             match seq_res {
                 Matched(pos, _) => {
                     {
-                        let seq_res = parse_identifier(input, state, pos);
+                        let seq_res = parse_word(input, state, pos);
                         match seq_res {
-                            Matched(pos, i) => {
+                            Matched(pos, name) => {
                                 {
                                     let match_str = &input[start_pos..pos];
-                                    Matched(pos, { i })
+                                    Matched(pos,
+                                            {
+                                                Ident{span:
+                                                          Span::new(start_pos,
+                                                                    pos),
+                                                      name: name,}
+                                            })
                                 }
                             }
                             Failed => Failed,
@@ -560,105 +883,86 @@ fn parse_symbol<'input>(input: &'input str, state: &mut ParseState<'input>,
         }
     }
 }
-fn parse_identifier<'input>(input: &'input str,
-                            state: &mut ParseState<'input>, pos: usize)
- -> RuleResult<String> {
+fn parse_label<'input>(input: &'input str, state: &mut ParseState<'input>,
+                       pos: usize) -> RuleResult<Ident> {
+    {
+        let start_pos = pos;
+        {
+            let seq_res = parse_word(input, state, pos);
+            match seq_res {
+                Matched(pos, name) => {
+                    {
+                        let match_str = &input[start_pos..pos];
+                        Matched(pos,
+                                {
+                                    Ident{span: Span::new(start_pos, pos),
+                                          name: name,}
+                                })
+                    }
+                }
+                Failed => Failed,
+            }
+        }
+    }
+}
+fn parse_word<'input>(input: &'input str, state: &mut ParseState<'input>,
+                      pos: usize) -> RuleResult<String> {
     {
         let start_pos = pos;
         {
             let seq_res =
                 {
-                    let start_pos = pos;
+                    let choice_res = parse_letter(input, state, pos);
+                    match choice_res {
+                        Matched(pos, value) => Matched(pos, value),
+                        Failed => slice_eq(input, state, pos, "_"),
+                    }
+                };
+            match seq_res {
+                Matched(pos, _) => {
                     {
                         let seq_res =
                             {
-                                let choice_res =
-                                    parse_letter(input, state, pos);
-                                match choice_res {
-                                    Matched(pos, value) =>
-                                    Matched(pos, value),
-                                    Failed =>
-                                    slice_eq(input, state, pos, "_"),
-                                }
-                            };
-                        match seq_res {
-                            Matched(pos, _) => {
-                                {
-                                    let seq_res =
+                                let mut repeat_pos = pos;
+                                loop  {
+                                    let pos = repeat_pos;
+                                    let step_res =
                                         {
-                                            let mut repeat_pos = pos;
-                                            loop  {
-                                                let pos = repeat_pos;
-                                                let step_res =
-                                                    {
-                                                        let choice_res =
-                                                            parse_letter(input,
-                                                                         state,
-                                                                         pos);
-                                                        match choice_res {
-                                                            Matched(pos,
-                                                                    value) =>
-                                                            Matched(pos,
-                                                                    value),
-                                                            Failed => {
-                                                                let choice_res =
-                                                                    parse_digit(input,
-                                                                                state,
-                                                                                pos);
-                                                                match choice_res
-                                                                    {
-                                                                    Matched(pos,
-                                                                            value)
-                                                                    =>
-                                                                    Matched(pos,
-                                                                            value),
-                                                                    Failed =>
-                                                                    slice_eq(input,
-                                                                             state,
-                                                                             pos,
-                                                                             "_"),
-                                                                }
-                                                            }
-                                                        }
-                                                    };
-                                                match step_res {
-                                                    Matched(newpos, value) =>
-                                                    {
-                                                        repeat_pos = newpos;
+                                            let choice_res =
+                                                parse_letter(input, state,
+                                                             pos);
+                                            match choice_res {
+                                                Matched(pos, value) =>
+                                                Matched(pos, value),
+                                                Failed => {
+                                                    let choice_res =
+                                                        parse_digit(input,
+                                                                    state,
+                                                                    pos);
+                                                    match choice_res {
+                                                        Matched(pos, value) =>
+                                                        Matched(pos, value),
+                                                        Failed =>
+                                                        slice_eq(input, state,
+                                                                 pos, "_"),
                                                     }
-                                                    Failed => { break ; }
                                                 }
                                             }
-                                            Matched(repeat_pos, ())
                                         };
-                                    match seq_res {
-                                        Matched(pos, _) => {
-                                            {
-                                                let match_str =
-                                                    &input[start_pos..pos];
-                                                Matched(pos,
-                                                        {
-                                                            match_str.to_string()
-                                                        })
-                                            }
+                                    match step_res {
+                                        Matched(newpos, value) => {
+                                            repeat_pos = newpos;
                                         }
-                                        Failed => Failed,
+                                        Failed => { break ; }
                                     }
                                 }
-                            }
-                            Failed => Failed,
-                        }
-                    }
-                };
-            match seq_res {
-                Matched(pos, chars) => {
-                    {
-                        let seq_res = parse___(input, state, pos);
+                                Matched(repeat_pos, ())
+                            };
                         match seq_res {
                             Matched(pos, _) => {
                                 {
                                     let match_str = &input[start_pos..pos];
-                                    Matched(pos, { chars })
+                                    Matched(pos, { match_str.to_string() })
                                 }
                             }
                             Failed => Failed,
@@ -671,7 +975,7 @@ fn parse_identifier<'input>(input: &'input str,
     }
 }
 fn parse_string<'input>(input: &'input str, state: &mut ParseState<'input>,
-                        pos: usize) -> RuleResult<String> {
+                        pos: usize) -> RuleResult<Str> {
     {
         let start_pos = pos;
         {
@@ -685,18 +989,16 @@ fn parse_string<'input>(input: &'input str, state: &mut ParseState<'input>,
                     }
                 };
             match seq_res {
-                Matched(pos, string) => {
+                Matched(pos, value) => {
                     {
-                        let seq_res = parse___(input, state, pos);
-                        match seq_res {
-                            Matched(pos, _) => {
+                        let match_str = &input[start_pos..pos];
+                        Matched(pos,
                                 {
-                                    let match_str = &input[start_pos..pos];
-                                    Matched(pos, { string })
-                                }
-                            }
-                            Failed => Failed,
-                        }
+                                    Str{span: Span::new(start_pos, pos),
+                                        value: value,
+                                        quote:
+                                            match_str.chars().next().unwrap(),}
+                                })
                     }
                 }
                 Failed => Failed,
@@ -1510,7 +1812,8 @@ fn parse_whitespace<'input>(input: &'input str,
                            "[ \t\u{a0}\u{feff}\u{1680}\u{180e}\u{2000}-\u{200a}\u{202f}\u{205f}\u{3000}]")
     }
 }
-pub fn parse<'input>(input: &'input str) -> ParseResult<Puppetfile> {
+pub fn parse<'input>(input: &'input str)
+ -> ParseResult<Vec<(Span, NodeKind)>> {
     let mut state = ParseState::new();
     match parse_parse(input, &mut state, 0) {
         Matched(pos, value) => { if pos == input.len() { return Ok(value) } }
@@ -1,30 +1,79 @@
 //! This library parses a Puppetfile
 #![deny(missing_docs)]
+#![allow(deprecated, ellipsis_inclusive_range_patterns, bare_trait_objects)]
+#![allow(clippy::redundant_field_names, clippy::redundant_static_lifetimes,
+         clippy::needless_borrowed_reference, clippy::match_like_matches_macro,
+         clippy::question_mark, clippy::unnecessary_map_or)]
 
 extern crate hyper;
+extern crate rand;
 extern crate semver;
+extern crate url;
 extern crate rustc_serialize;
+#[cfg(test)]
+extern crate quickcheck;
 
 use std::error::Error;
 use std::fmt;
-use std::io::{self, Read};
+use std::io;
+use std::path::PathBuf;
 
-use hyper::Client;
 use rustc_serialize::json;
 use semver::VersionReq;
 
 use ErrorKind::*;
 
+pub use auth::{ForgeAuth, ForgeCredentials, Token};
+pub use cache::{CachingTransport, DEFAULT_TTL};
+pub use check::{VersionCheck, DEFAULT_CONCURRENCY};
+pub use collision::{Collision, CollisionKind};
+pub use diagnostic::Renderer;
+pub use forge::ForgeClient;
+pub use formatter::{FormatStyle, line_diff};
+pub use lint::{Lint, LintConfig, Severity};
+pub use name::ModuleName;
+pub use sort::SortOrder;
+pub use retry::RetryPolicy;
+pub use source::{ModuleSource, ForgeVersion, GitRef, KNOWN_OPTIONS};
+pub use syntax::{SyntaxTree, Span, Location, LineColumn, ModuleLocation};
+pub use transport::{Transport, Request, Response, HyperTransport, FakeTransport,
+                    DEFAULT_USER_AGENT, DEFAULT_TIMEOUT};
+
+mod auth;
+mod cache;
+mod check;
+mod collision;
+mod config;
+mod diagnostic;
+mod edit;
+pub mod forge;
+mod formatter;
+#[allow(clippy::all)]
 mod grammar;
+pub mod lint;
+mod name;
+mod retry;
+mod sort;
+mod source;
+pub mod syntax;
+mod transport;
 
 #[cfg(test)]
 mod test;
 
+/// The API of the forge r10k uses if the Puppetfile does not declare one
+pub const DEFAULT_FORGE: &'static str = "https://forgeapi.puppetlabs.com";
+
+/// The directory modules are installed into if no `moduledir` is given
+pub const DEFAULT_MODULEDIR: &'static str = "modules";
+
 /// This represents a Puppetfile
 #[derive(PartialEq, Clone, Debug)]
 pub struct Puppetfile {
-    /// The forge URL
-    pub forge: String,
+    /// The forge URL, if declared
+    pub forge: Option<String>,
+    /// The directory modules are installed into, relative to the Puppetfile
+    pub moduledir: Option<String>,
     /// All Modules contained in the Puppetfile
     pub modules: Vec<Module>,
 }
@@ -32,17 +81,86 @@ pub struct Puppetfile {
 impl Puppetfile {
     /// Try parsing the contents of a Puppetfile into a Puppetfile struct
     pub fn parse(contents: &str) -> Result<Puppetfile, PuppetfileError> {
-        Ok(try!(grammar::parse(contents)))
+        try!(SyntaxTree::parse(contents)).to_puppetfile()
+    }
+
+    /// Parses as much of a Puppetfile as possible and returns every error found on the way
+    ///
+    /// Statements with syntax errors or invalid versions are skipped, everything else ends up
+    /// in the returned `Puppetfile`.
+    pub fn parse_recovering(contents: &str) -> (Puppetfile, Vec<PuppetfileError>) {
+        let (tree, mut errors) = SyntaxTree::parse_recovering(contents);
+        let (puppetfile, invalid) = tree.to_puppetfile_recovering();
+        errors.extend(invalid);
+        (puppetfile, errors)
+    }
+
+    /// The declared forge URL or the Puppet Forge r10k falls back to
+    pub fn forge_url(&self) -> &str {
+        match self.forge {
+            Some(ref forge) => forge,
+            None => DEFAULT_FORGE,
+        }
+    }
+
+    /// The configured `moduledir` or the r10k default `modules`
+    pub fn moduledir(&self) -> &str {
+        match self.moduledir {
+            Some(ref moduledir) => moduledir,
+            None => DEFAULT_MODULEDIR,
+        }
+    }
+
+    /// Switches the options of all modules to the given syntax
+    pub fn set_hash_style(&mut self, style: HashStyle) {
+        for module in self.modules.iter_mut() {
+            module.set_hash_style(style);
+        }
+    }
+
+    /// The path the module gets installed to, relative to the Puppetfile
+    ///
+    /// An `:install_path` option of the module replaces the `moduledir`.
+    pub fn install_path(&self, module: &Module) -> PathBuf {
+        let dir = module.info
+                        .iter()
+                        .filter_map(|info| {
+                            match *info {
+                                ModuleInfo::Info(ref key, ref value, _) if key == "install_path" => {
+                                    Some(value.as_str())
+                                }
+                                _ => None,
+                            }
+                        })
+                        .next()
+                        .unwrap_or(self.moduledir());
+        PathBuf::from(dir).join(module.install_name())
     }
 }
 impl fmt::Display for Puppetfile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let res = write!(f, "forge '{}'\n\n", self.forge);
+        let mut res = Ok(());
+        if let Some(ref forge) = self.forge {
+            res = res.and(writeln!(f, "forge {}", edit::quote(forge, '\'')));
+        }
+        if let Some(ref moduledir) = self.moduledir {
+            res = res.and(writeln!(f, "moduledir {}", edit::quote(moduledir, '\'')));
+        }
+        if self.forge.is_some() || self.moduledir.is_some() {
+            res = res.and(writeln!(f));
+        }
         self.modules.iter().fold(res,
                                  |prev_res, module| prev_res.and(write!(f, "\n{}\n", module)))
     }
 }
 
+/// Converts a byte offset into a one-based line and column
+fn line_column(contents: &str, pos: usize) -> (usize, usize) {
+    let before = &contents[..pos];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
 
 /// The representation of a puppet module
 #[derive(PartialEq, Clone, Debug)]
@@ -53,10 +171,6 @@ pub struct Module {
     pub info: Vec<ModuleInfo>,
 }
 
-#[derive(RustcDecodable)]
-struct ForgeVersionResponse {
-    version: String,
-}
 
 /// represents the type of error of a PuppetfileError
 #[derive(Debug)]
@@ -71,8 +185,80 @@ pub enum ErrorKind {
     JsonError(json::DecoderError),
     /// an error while building the forge URL
     UrlBuilding,
+    /// the forge answered with an unsuccessful HTTP status
+    HttpStatus {
+        /// the requested URL
+        url: String,
+        /// the HTTP status code, e.g. 403
+        status: u16,
+    },
+    /// the forge answered 404
+    NotFound {
+        /// the requested URL
+        url: String,
+    },
+    /// the module is not published on the forge
+    ModuleNotFound {
+        /// the name of the module as given
+        module: String,
+        /// the URL of the forge
+        forge: String,
+        /// similarly named modules on the forge, closest first
+        suggestions: Vec<String>,
+    },
+    /// the forge answered 429 after all retries
+    RateLimited {
+        /// the requested URL
+        url: String,
+        /// the seconds the forge asked to wait, if given
+        retry_after: Option<u64>,
+    },
+    /// the forge answered with a 5xx status after all retries
+    ServerError {
+        /// the requested URL
+        url: String,
+        /// the HTTP status code, e.g. 503
+        status: u16,
+    },
     /// an HTTP error
     ParseError(grammar::ParseError),
+    /// a directive that may only appear once was declared again
+    DuplicateDirective {
+        /// the name of the directive, e.g. `forge`
+        directive: String,
+        /// line of the second declaration
+        line: usize,
+        /// column of the second declaration
+        column: usize,
+    },
+    /// the options of the named module contradict each other
+    InvalidSource {
+        /// the name of the module
+        module: String,
+        /// the offending option or the module name, `None` if the module was not parsed
+        location: Option<Location>,
+    },
+    /// a module version that is not a valid version requirement
+    InvalidVersion {
+        /// the version as written in the Puppetfile
+        version: String,
+        /// location of the quoted version
+        location: Location,
+    },
+    /// the named module is not declared in the Puppetfile
+    UnknownModule(String),
+    /// the named module is already declared in the Puppetfile
+    ModuleExists(String),
+    /// a module name that is neither `owner/name`, `owner-name` nor `name`
+    InvalidModuleName(String),
+    /// an invalid line in a lint config, 0 if the config was not read from a file
+    InvalidLintConfig(usize),
+    /// the URL is not cached and the forge client is offline
+    NotCached(String),
+    /// an invalid line in a forge config
+    InvalidForgeConfig(usize),
+    /// the version lookup of the named module panicked
+    LookupPanicked(String),
 }
 /// represents an error while checking the version published on the forge
 #[derive(Debug)]
@@ -151,41 +337,45 @@ impl Error for PuppetfileError {
 impl Module {
     /// The current version of the module returned from the forge API
     pub fn forge_version(&self, forge_url: &str) -> Result<semver::Version, PuppetfileError> {
-        let url = try!(self.version_url(forge_url));
-        let client = Client::new();
-        let mut response = try!(client.get(&*url).send());
-        let mut response_string = String::new();
-        try!(response.read_to_string(&mut response_string));
-        let version_struct: ForgeVersionResponse = try!(json::decode(&response_string));
-        let version = try!(semver::Version::parse(&version_struct.version));
+        self.forge_version_with(&ForgeClient::new(forge_url))
+    }
 
-        Ok(version)
+    /// The current version of the module returned by the given forge client
+    pub fn forge_version_with<T: Transport>(&self,
+                                            client: &ForgeClient<T>)
+                                            -> Result<semver::Version, PuppetfileError> {
+        let name = try!(self.module_name());
+        let release = try!(client.current_release(&name));
+        release.version()
     }
 
-    /// Builds the URL for the forge API for fetching the version
+    /// Builds the URL for the forge API for fetching the module
     pub fn version_url(&self, forge_url: &str) -> Result<String, PuppetfileError> {
-        let stripped_url = match forge_url.ends_with("/") {
-            true => &forge_url[..forge_url.len() - 1],
-            _ => &forge_url[..],
-        };
-        let (user, mod_name) = match self.user_name_pair() {
-            Some((user, mod_name)) => (user, mod_name),
-            None => return Err(From::from((UrlBuilding, "Could not build url".to_string()))),
-        };
-
-        Ok(format!("{}/users/{}/modules/{}/releases/find.json",
-                   stripped_url,
-                   user,
-                   mod_name))
+        let name = try!(self.module_name());
+        ForgeClient::new(forge_url).module_url(&name)
     }
 
-    /// Returns user and module name from 'user/mod_name'
+    /// Returns user and module name from 'user/mod_name' or 'user-mod_name'
     pub fn user_name_pair(&self) -> Option<(&str, &str)> {
-        if self.name.contains("/") {
-            let mut parts = self.name.split('/');
-            Some((parts.next().unwrap(), parts.next().unwrap()))
-        } else {
-            None
+        self.name
+            .find(&['/', '-'][..])
+            .map(|i| (&self.name[..i], &self.name[i + 1..]))
+    }
+
+    /// Returns the name of the directory the module is installed into
+    pub fn install_name(&self) -> &str {
+        match self.user_name_pair() {
+            Some((_, mod_name)) => mod_name,
+            None => &self.name,
+        }
+    }
+
+    /// Switches all options of the module to the given syntax
+    pub fn set_hash_style(&mut self, style: HashStyle) {
+        for info in self.info.iter_mut() {
+            if let ModuleInfo::Info(_, _, ref mut s) = *info {
+                *s = style;
+            }
         }
     }
 
@@ -193,8 +383,8 @@ impl Module {
     pub fn version(&self) -> Option<&VersionReq> {
         for info in self.info.iter() {
             match *info {
-                ModuleInfo::Version(ref v) => return Some(v),
-                ModuleInfo::Info(..) => (),
+                ModuleInfo::Version(ref v, _) => return Some(v),
+                ModuleInfo::Latest | ModuleInfo::Info(..) => (),
             }
         }
         None
@@ -202,10 +392,13 @@ impl Module {
 }
 impl fmt::Display for Module {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let res = write!(f, "mod '{}'", self.name);
+        let res = write!(f, "mod {}", edit::quote(&self.name, '\''));
         self.info.iter().fold(res, |prev_res, mod_info| {
             match *mod_info {
-                ModuleInfo::Version(..) => prev_res.and(write!(f, ", '{}'", mod_info)),
+                ModuleInfo::Version(_, ref spec) => {
+                    prev_res.and(write!(f, ", {}", edit::quote(spec, '\'')))
+                }
+                ModuleInfo::Latest => prev_res.and(write!(f, ", {}", mod_info)),
                 ModuleInfo::Info(..) => prev_res.and(write!(f, ",\n  {}", mod_info)),
             }
         })
@@ -216,17 +409,31 @@ impl fmt::Display for Module {
 /// Further Information on Puppet Modules
 #[derive(PartialEq, Clone, Debug)]
 pub enum ModuleInfo {
-    /// Version as String
-    Version(VersionReq),
-    /// Key Value based Information
-    Info(String, String),
+    /// Version requirement and the version as written, e.g. `1.0.0` or `>= 1.0.0`
+    Version(VersionReq, String),
+    /// `:latest`, always the newest release on the forge
+    Latest,
+    /// Key Value based Information and the syntax it was written in
+    Info(String, Value, HashStyle),
 }
 impl ModuleInfo {
+    /// Builds a `Version` from a version as written in a Puppetfile
+    ///
+    /// A plain version like `1.0.0` pins exactly that release.
+    pub fn version(spec: &str) -> Result<ModuleInfo, semver::ReqParseError> {
+        let req = if semver::Version::parse(spec).is_ok() {
+            VersionReq::parse(&format!("={}", spec))
+        } else {
+            VersionReq::parse(spec)
+        };
+        req.map(|req| ModuleInfo::Version(req, spec.to_string()))
+    }
+
     /// Returns `true` if the option is a `Version` value
     pub fn is_version(&self) -> bool {
         match *self {
             ModuleInfo::Version(..) => true,
-            ModuleInfo::Info(..) => false,
+            ModuleInfo::Latest | ModuleInfo::Info(..) => false,
         }
     }
 }
@@ -234,8 +441,71 @@ impl ModuleInfo {
 impl fmt::Display for ModuleInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ModuleInfo::Version(ref v) => write!(f, "{}", v),
-            ModuleInfo::Info(ref k, ref v) => write!(f, ":{} => '{}'", k, v),
+            ModuleInfo::Version(_, ref spec) => write!(f, "{}", spec),
+            ModuleInfo::Latest => write!(f, ":latest"),
+            ModuleInfo::Info(ref k, ref v, HashStyle::HashRocket) => write!(f, ":{} => {}", k, v),
+            ModuleInfo::Info(ref k, ref v, HashStyle::Ruby19) => write!(f, "{}: {}", k, v),
+        }
+    }
+}
+
+/// The syntax of a module option
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HashStyle {
+    /// `:key => value`
+    HashRocket,
+    /// `key: value`, available since Ruby 1.9
+    Ruby19,
+}
+
+/// The value of a module option
+#[derive(PartialEq, Clone, Debug)]
+pub enum Value {
+    /// A quoted string
+    String(String),
+    /// `:control_branch`, the branch of the control repository being deployed
+    ControlBranch,
+    /// Any other Ruby symbol, without the leading colon
+    Symbol(String),
+    /// A bare `true` or `false`, e.g. of `:local => true`
+    Boolean(bool),
+}
+impl Value {
+    /// Returns the string value, the symbol name or `true` or `false`
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Value::String(ref s) | Value::Symbol(ref s) => s,
+            Value::ControlBranch => "control_branch",
+            Value::Boolean(true) => "true",
+            Value::Boolean(false) => "false",
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Boolean(b)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::String(ref s) => write!(f, "{}", edit::quote(s, '\'')),
+            Value::ControlBranch | Value::Symbol(..) => write!(f, ":{}", self.as_str()),
+            Value::Boolean(..) => write!(f, "{}", self.as_str()),
         }
     }
 }
@@ -0,0 +1,402 @@
+//! Policy checks for Puppetfiles
+//!
+//! Every rule has an id and a default severity. A config file can change the severity of a
+//! rule or turn it off, a `# puppetfile:disable=rule,other-rule` comment in front of, inside or
+//! behind a statement turns rules off for that statement only.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use semver;
+
+use super::{Puppetfile, Module, ModuleInfo, ModuleSource, ForgeVersion, GitRef, PuppetfileError};
+use collision::{duplicate_of, duplicate_message};
+use config::{ConfigLine, parse_line};
+use syntax::{SyntaxTree, NodeKind, Location};
+use ErrorKind::InvalidLintConfig;
+
+/// How serious a violation of a rule is
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Severity {
+    /// Reported, but does not fail a check
+    Warning,
+    /// Fails a check
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A lint rule
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Rule {
+    /// The id used in config files and `puppetfile:disable` comments
+    pub id: &'static str,
+    /// What the rule checks
+    pub description: &'static str,
+    /// The severity unless configured otherwise
+    pub severity: Severity,
+}
+
+/// All rules of the linter
+pub const RULES: &'static [Rule] = &[Rule {
+                                         id: "duplicate-module",
+                                         description: "a module is declared more than once, \
+                                                       `a/b` and `a-b` are the same module",
+                                         severity: Severity::Error,
+                                     },
+                                     Rule {
+                                         id: "unpinned-forge-module",
+                                         description: "a forge module without an exact version",
+                                         severity: Severity::Warning,
+                                     },
+                                     Rule {
+                                         id: "git-branch",
+                                         description: "a git module tracking a branch instead \
+                                                       of a tag or commit",
+                                         severity: Severity::Warning,
+                                     },
+                                     Rule {
+                                         id: "insecure-url",
+                                         description: "a repository URL using `http://` or \
+                                                       `git://`",
+                                         severity: Severity::Error,
+                                     },
+                                     Rule {
+                                         id: "unknown-option",
+                                         description: "an option r10k does not know",
+                                         severity: Severity::Warning,
+                                     },
+                                     Rule {
+                                         id: "conflicting-options",
+                                         description: "options of a module that contradict each \
+                                                       other",
+                                         severity: Severity::Error,
+                                     },
+                                     Rule {
+                                         id: "insecure-forge",
+                                         description: "a forge URL not using `https://`",
+                                         severity: Severity::Warning,
+                                     }];
+
+/// A violation of a rule
+#[derive(PartialEq, Clone, Debug)]
+pub struct Lint {
+    /// The id of the violated rule
+    pub rule: &'static str,
+    /// The configured severity of the rule
+    pub severity: Severity,
+    /// What is wrong
+    pub message: String,
+    /// The module the lint is about, `None` for `forge`
+    pub module: Option<String>,
+    /// Where the problem is, `None` if the lint was not created from source
+    pub location: Option<Location>,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}[{}]: {}", self.severity, self.rule, self.message));
+        match self.location {
+            Some(ref location) => {
+                write!(f, " at {}:{}", location.start.line, location.start.column)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The severities of all rules
+#[derive(PartialEq, Clone, Debug)]
+pub struct LintConfig {
+    rules: HashMap<&'static str, Option<Severity>>,
+}
+
+impl Default for LintConfig {
+    fn default() -> LintConfig {
+        LintConfig { rules: RULES.iter().map(|rule| (rule.id, Some(rule.severity))).collect() }
+    }
+}
+
+impl LintConfig {
+    /// Parses a config file with one `rule-id = "severity"` line per rule
+    ///
+    /// The severity is `error`, `warning` or `off`, the quotes are optional. `#` outside of
+    /// quotes starts a comment and a `[rules]` header is ignored, so the file is valid TOML.
+    pub fn parse(contents: &str) -> Result<LintConfig, PuppetfileError> {
+        let mut config = LintConfig::default();
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |reason: String| -> PuppetfileError {
+                From::from((InvalidLintConfig(i + 1),
+                            format!("invalid lint config on line {}: {}", i + 1, reason)))
+            };
+            let (rule, value) = match parse_line(line) {
+                Ok(ConfigLine::Blank) => continue,
+                Ok(ConfigLine::Section(ref section)) if section == "rules" => continue,
+                Ok(ConfigLine::Section(section)) => {
+                    return Err(invalid(format!("unknown section `{}`, expected `rules`", section)))
+                }
+                Ok(ConfigLine::Pair(rule, value)) => (rule, value),
+                Err(reason) => return Err(invalid(reason.to_string())),
+            };
+            let severity = match &value[..] {
+                "error" => Some(Severity::Error),
+                "warning" => Some(Severity::Warning),
+                "off" => None,
+                _ => {
+                    return Err(invalid(format!("unknown severity `{}`, expected `error`, \
+                                                `warning` or `off`",
+                                               value)))
+                }
+            };
+            try!(config.set(&rule, severity).map_err(|err| invalid(err.desc)));
+        }
+        Ok(config)
+    }
+
+    /// Reads and parses a config file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LintConfig, PuppetfileError> {
+        let mut contents = String::new();
+        try!(try!(File::open(path)).read_to_string(&mut contents));
+        LintConfig::parse(&contents)
+    }
+
+    /// The severity of a rule, `None` if it is turned off
+    pub fn severity(&self, rule: &str) -> Option<Severity> {
+        self.rules.get(rule).cloned().unwrap_or(None)
+    }
+
+    /// Changes the severity of a rule, `None` turns it off
+    pub fn set(&mut self, rule: &str, severity: Option<Severity>) -> Result<(), PuppetfileError> {
+        match RULES.iter().find(|r| r.id == rule) {
+            Some(r) => {
+                self.rules.insert(r.id, severity);
+                Ok(())
+            }
+            None => {
+                Err(From::from((InvalidLintConfig(0), format!("unknown lint rule `{}`", rule))))
+            }
+        }
+    }
+}
+
+/// Lints the contents of a Puppetfile, honouring `puppetfile:disable` comments
+pub fn lint(contents: &str, config: &LintConfig) -> Result<Vec<Lint>, PuppetfileError> {
+    let tree = try!(SyntaxTree::parse(contents));
+    let modules = try!(tree.modules());
+    let locations = tree.module_locations();
+    let mut modules = modules.iter().zip(locations.iter());
+    let mut earlier: Vec<&Module> = vec![];
+    let mut lints = vec![];
+    for node in tree.nodes.iter() {
+        let disabled = disabled_rules(&node.leading
+                                           .comments()
+                                           .into_iter()
+                                           .chain(node.trailing.comments())
+                                           .chain(node.text
+                                                      .lines()
+                                                      .filter_map(|line| {
+                                                          line.find('#').map(|i| &line[i..])
+                                                      }))
+                                           .collect::<Vec<_>>());
+        let (findings, module) = match node.kind {
+            NodeKind::Forge(ref url) => (check_forge(&url.value), None),
+            NodeKind::Moduledir(..) => (vec![], None),
+            NodeKind::Module(..) => {
+                let (module, location) = modules.next().unwrap();
+                (check_module(module, &earlier), Some((module, location)))
+            }
+        };
+        let module_location = module.map(|(_, location)| location);
+        let module = module.map(|(module, _)| module);
+        for finding in findings {
+            if disabled.iter().any(|rule| rule == "all" || rule == finding.rule) {
+                continue;
+            }
+            let statement = Some(Location::from_span(contents, node.span));
+            let location = match (module, module_location, &finding.target) {
+                (Some(module), Some(at), &Target::Version) => {
+                    at.version_location(module).or(statement)
+                }
+                (Some(module), Some(at), &Target::Option(ref key)) => {
+                    at.option_location(module, key).or(statement)
+                }
+                _ => statement,
+            };
+            lints.extend(finding.lint(config, module, location));
+        }
+        if let Some(module) = module {
+            earlier.push(module);
+        }
+    }
+    Ok(lints)
+}
+
+/// Lints a `Puppetfile` without source, the lints have no location
+pub fn lint_puppetfile(puppetfile: &Puppetfile, config: &LintConfig) -> Vec<Lint> {
+    let mut lints = vec![];
+    for finding in puppetfile.forge.as_ref().map(|forge| check_forge(forge)).unwrap_or(vec![]) {
+        lints.extend(finding.lint(config, None, None));
+    }
+    for (i, module) in puppetfile.modules.iter().enumerate() {
+        let earlier: Vec<&Module> = puppetfile.modules[..i].iter().collect();
+        for finding in check_module(module, &earlier) {
+            lints.extend(finding.lint(config, Some(module), None));
+        }
+    }
+    lints
+}
+
+/// The part of a statement a finding is about
+enum Target {
+    Statement,
+    Version,
+    Option(String),
+}
+
+struct Finding {
+    rule: &'static str,
+    message: String,
+    target: Target,
+}
+
+impl Finding {
+    fn new(rule: &'static str, message: String, target: Target) -> Finding {
+        Finding {
+            rule: rule,
+            message: message,
+            target: target,
+        }
+    }
+
+    fn lint(self,
+            config: &LintConfig,
+            module: Option<&Module>,
+            location: Option<Location>)
+            -> Option<Lint> {
+        config.severity(self.rule).map(|severity| {
+            Lint {
+                rule: self.rule,
+                severity: severity,
+                message: self.message,
+                module: module.map(|module| module.name.clone()),
+                location: location,
+            }
+        })
+    }
+}
+
+fn check_forge(url: &str) -> Vec<Finding> {
+    if url.starts_with("https://") {
+        vec![]
+    } else {
+        vec![Finding::new("insecure-forge",
+                          format!("the forge '{}' is not accessed via https", url),
+                          Target::Statement)]
+    }
+}
+
+fn check_module(module: &Module, earlier: &[&Module]) -> Vec<Finding> {
+    let mut findings = vec![];
+    if let Some(first) = duplicate_of(module, earlier) {
+        findings.push(Finding::new("duplicate-module",
+                                   duplicate_message(&earlier[first].name, &module.name),
+                                   Target::Statement));
+    }
+
+    for (key, _) in module.unknown_options() {
+        findings.push(Finding::new("unknown-option",
+                                   format!("r10k does not know the option `:{}`", key),
+                                   Target::Option(key.to_string())));
+    }
+    for info in module.info.iter() {
+        if let ModuleInfo::Info(ref key, ref value, _) = *info {
+            let url = value.as_str();
+            if (key == "git" || key == "svn") &&
+               (url.starts_with("http://") || url.starts_with("git://")) {
+                findings.push(Finding::new("insecure-url",
+                                           format!("'{}' is fetched without encryption", url),
+                                           Target::Option(key.clone())));
+            }
+        }
+    }
+
+    match module.source() {
+        Ok(ModuleSource::Forge(ForgeVersion::Any)) => {
+            findings.push(Finding::new("unpinned-forge-module",
+                                       format!("module '{}' has no version", module.name),
+                                       Target::Statement))
+        }
+        Ok(ModuleSource::Forge(ForgeVersion::Latest)) => {
+            findings.push(Finding::new("unpinned-forge-module",
+                                       format!("module '{}' follows the latest release",
+                                               module.name),
+                                       Target::Version))
+        }
+        Ok(ModuleSource::Forge(ForgeVersion::Req(..))) if !is_pinned(module) => {
+            findings.push(Finding::new("unpinned-forge-module",
+                                       format!("module '{}' is not pinned to one release",
+                                               module.name),
+                                       Target::Version))
+        }
+        Ok(ModuleSource::Git { reference: None, .. }) => {
+            findings.push(Finding::new("git-branch",
+                                       format!("module '{}' follows the default branch",
+                                               module.name),
+                                       Target::Option("git".to_string())))
+        }
+        Ok(ModuleSource::Git { reference: Some(GitRef::Branch(ref branch)), .. }) => {
+            findings.push(Finding::new("git-branch",
+                                       format!("module '{}' follows the branch '{}'",
+                                               module.name,
+                                               branch),
+                                       Target::Option("branch".to_string())))
+        }
+        Ok(ModuleSource::Git { reference: Some(GitRef::ControlBranch), .. }) => {
+            findings.push(Finding::new("git-branch",
+                                       format!("module '{}' follows the branch of the control \
+                                                repository",
+                                               module.name),
+                                       Target::Option("branch".to_string())))
+        }
+        Ok(..) => (),
+        Err(err) => findings.push(Finding::new("conflicting-options", err.desc, Target::Statement)),
+    }
+    findings
+}
+
+/// Returns `true` if the version of the module names exactly one release
+fn is_pinned(module: &Module) -> bool {
+    module.info.iter().any(|info| {
+        match *info {
+            ModuleInfo::Version(_, ref spec) => {
+                semver::Version::parse(spec.trim_left_matches('=').trim()).is_ok()
+            }
+            ModuleInfo::Latest | ModuleInfo::Info(..) => false,
+        }
+    })
+}
+
+/// Collects the rules turned off by `puppetfile:disable=` comments
+fn disabled_rules(comments: &[&str]) -> Vec<String> {
+    let mut rules = vec![];
+    for comment in comments {
+        if let Some(start) = comment.find("puppetfile:disable=") {
+            let list = comment[start + "puppetfile:disable=".len()..]
+                           .split_whitespace()
+                           .next()
+                           .unwrap_or("");
+            rules.extend(list.split(',').filter(|rule| !rule.is_empty()).map(|rule| rule.to_string()));
+        }
+    }
+    rules
+}
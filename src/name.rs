@@ -0,0 +1,110 @@
+//! Module names in the `owner/name` and `owner-name` spellings
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use super::{Module, PuppetfileError};
+use ErrorKind::InvalidModuleName;
+
+/// The name of a module, optionally prefixed by its owner
+///
+/// r10k and the forge treat `puppetlabs/stdlib` and `puppetlabs-stdlib` as the same module,
+/// so the separator is ignored when comparing names. It is only kept to print the name as it
+/// was written.
+#[derive(Clone, Debug)]
+pub struct ModuleName {
+    /// The user or organization the module belongs to, e.g. `puppetlabs`
+    pub owner: Option<String>,
+    /// The name of the module, e.g. `stdlib`
+    pub name: String,
+    /// The separator between owner and name, `/` or `-`
+    pub separator: char,
+}
+
+impl ModuleName {
+    /// Parses a module name the way r10k does
+    ///
+    /// Owner and name may only contain ASCII letters, digits and `_`.
+    pub fn parse(name: &str) -> Result<ModuleName, PuppetfileError> {
+        let (owner, separator, rest) = match name.find(&['/', '-'][..]) {
+            Some(i) => (Some(&name[..i]), name[i..].chars().next().unwrap(), &name[i + 1..]),
+            None => (None, '/', name),
+        };
+        let valid = |part: &str| {
+            !part.is_empty() &&
+            part.chars().all(|c| {
+                match c {
+                    'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => true,
+                    _ => false,
+                }
+            })
+        };
+        if !owner.map(valid).unwrap_or(true) || !valid(rest) {
+            return Err(From::from((InvalidModuleName(name.to_string()),
+                                   format!("invalid module name '{}', expected `owner/name`, \
+                                            `owner-name` or `name`",
+                                           name))));
+        }
+        Ok(ModuleName {
+            owner: owner.map(|owner| owner.to_string()),
+            name: rest.to_string(),
+            separator: separator,
+        })
+    }
+
+    /// The name in the `owner-name` form the forge API uses
+    pub fn slug(&self) -> String {
+        match self.owner {
+            Some(ref owner) => format!("{}-{}", owner, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl PartialEq for ModuleName {
+    fn eq(&self, other: &ModuleName) -> bool {
+        self.owner == other.owner && self.name == other.name
+    }
+}
+
+impl Eq for ModuleName {}
+
+impl Hash for ModuleName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.owner.hash(state);
+        self.name.hash(state);
+    }
+}
+
+impl FromStr for ModuleName {
+    type Err = PuppetfileError;
+
+    fn from_str(name: &str) -> Result<ModuleName, PuppetfileError> {
+        ModuleName::parse(name)
+    }
+}
+
+impl fmt::Display for ModuleName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.owner {
+            Some(ref owner) => write!(f, "{}{}{}", owner, self.separator, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl Module {
+    /// Returns the typed name of the module
+    pub fn module_name(&self) -> Result<ModuleName, PuppetfileError> {
+        ModuleName::parse(&self.name)
+    }
+}
+
+/// Returns `true` if both names denote the same module, regardless of the separator
+pub fn same_module(a: &str, b: &str) -> bool {
+    match (ModuleName::parse(a), ModuleName::parse(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,63 @@
+//! When the forge client retries failed requests
+
+use std::cmp;
+use std::time::Duration;
+
+use rand::{self, Rng};
+
+/// How often and how long to wait before retrying a rate limited request, a server error
+/// or a failed connection
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// How often a request is retried
+    pub retries: u32,
+    /// The delay before the first retry, doubled for every further retry
+    pub base_delay: Duration,
+    /// The longest delay, the request fails if the forge asks to wait longer
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries
+    pub fn none() -> RetryPolicy {
+        RetryPolicy { retries: 0, ..RetryPolicy::default() }
+    }
+
+    /// The delay before retry number `attempt`, counted from 0, or `None` to give up
+    ///
+    /// `retry_after` is the `Retry-After` header in seconds, without it the delay grows
+    /// exponentially with random jitter.
+    pub fn delay(&self, attempt: u32, retry_after: Option<u64>) -> Option<Duration> {
+        if attempt >= self.retries {
+            return None;
+        }
+        match retry_after {
+            Some(seconds) if Duration::from_secs(seconds) > self.max_delay => None,
+            Some(seconds) => Some(Duration::from_secs(seconds)),
+            None => {
+                let base = millis(self.base_delay);
+                let delay = base.saturating_mul(1 << cmp::min(attempt, 32));
+                Some(jitter(cmp::min(delay, millis(self.max_delay))))
+            }
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1_000_000
+}
+
+/// Between half and all of the delay, random to spread out clients retrying at once
+fn jitter(millis: u64) -> Duration {
+    Duration::from_millis(rand::thread_rng().gen_range(millis / 2, millis + 1))
+}
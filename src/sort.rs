@@ -0,0 +1,40 @@
+//! Sorting modules by name and source
+
+use super::{Puppetfile, Module, ModuleSource};
+
+/// The order `sort_modules` puts modules in
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SortOrder {
+    /// Alphabetically by the normalized module name
+    Name,
+    /// Forge modules first, then git, subversion and local modules, each sorted by name
+    SourceAndName,
+}
+
+/// The key modules are sorted by, stable for modules that compare equal
+pub fn sort_key(order: SortOrder, module: &Module) -> (usize, String) {
+    let group = match order {
+        SortOrder::Name => 0,
+        SortOrder::SourceAndName => {
+            match module.source() {
+                Ok(ModuleSource::Forge(..)) => 0,
+                Ok(ModuleSource::Git { .. }) => 1,
+                Ok(ModuleSource::Svn { .. }) => 2,
+                Ok(ModuleSource::Local) => 3,
+                Err(..) => 4,
+            }
+        }
+    };
+    let name = match module.module_name() {
+        Ok(name) => name.slug(),
+        Err(..) => module.name.clone(),
+    };
+    (group, name.to_lowercase())
+}
+
+impl Puppetfile {
+    /// Sorts the modules, modules that compare equal keep their order
+    pub fn sort_modules(&mut self, order: SortOrder) {
+        self.modules.sort_by_key(|module| sort_key(order, module));
+    }
+}
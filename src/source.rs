@@ -0,0 +1,241 @@
+//! Typed view on where a module is installed from
+
+use semver::VersionReq;
+
+use super::{Module, ModuleInfo, PuppetfileError, Value, Location, ModuleLocation};
+use ErrorKind::InvalidSource;
+
+/// Option keys r10k understands, everything else is reported by `Module::unknown_options`
+pub const KNOWN_OPTIONS: &'static [&'static str] = &["git",
+                                                     "branch",
+                                                     "tag",
+                                                     "commit",
+                                                     "ref",
+                                                     "default_branch",
+                                                     "install_path",
+                                                     "svn",
+                                                     "rev",
+                                                     "revision",
+                                                     "username",
+                                                     "password",
+                                                     "local"];
+
+const GIT_REFS: &'static [&'static str] = &["branch", "tag", "commit", "ref"];
+const SVN_REVISIONS: &'static [&'static str] = &["rev", "revision"];
+
+/// Where a module is installed from
+#[derive(PartialEq, Clone, Debug)]
+pub enum ModuleSource {
+    /// A module released on the forge
+    Forge(ForgeVersion),
+    /// A module cloned from a git repository
+    Git {
+        /// URL of the repository
+        url: String,
+        /// The ref to check out, the default branch if `None`
+        reference: Option<GitRef>,
+    },
+    /// A module checked out from a subversion repository
+    Svn {
+        /// URL of the repository
+        url: String,
+        /// The revision to check out, `HEAD` if `None`
+        revision: Option<String>,
+    },
+    /// `:local => true`, a module that lives in the moduledir and is not managed by r10k
+    Local,
+}
+
+/// The version of a forge module
+#[derive(PartialEq, Clone, Debug)]
+pub enum ForgeVersion {
+    /// No version given, r10k installs the newest release once and keeps it
+    Any,
+    /// `:latest`, r10k updates to the newest release on every deployment
+    Latest,
+    /// A version requirement
+    Req(VersionReq),
+}
+
+/// The ref of a git module
+#[derive(PartialEq, Clone, Debug)]
+pub enum GitRef {
+    /// `:branch`, follows the branch
+    Branch(String),
+    /// `:branch => :control_branch`, follows the branch of the control repository
+    ControlBranch,
+    /// `:tag`, a fixed tag
+    Tag(String),
+    /// `:commit`, a fixed commit hash
+    Commit(String),
+    /// `:ref`, any ref that git can resolve
+    Ref(String),
+}
+
+impl GitRef {
+    fn new(key: &str, value: &Value) -> Option<GitRef> {
+        let value = match (key, value) {
+            ("branch", &Value::ControlBranch) => return Some(GitRef::ControlBranch),
+            (_, &Value::String(ref value)) => value.clone(),
+            _ => return None,
+        };
+        Some(match key {
+            "branch" => GitRef::Branch(value),
+            "tag" => GitRef::Tag(value),
+            "commit" => GitRef::Commit(value),
+            _ => GitRef::Ref(value),
+        })
+    }
+}
+
+impl Module {
+    /// Returns the typed source of the module built from its `info`
+    ///
+    /// Fails if the options contradict each other, e.g. `:tag` together with `:branch`
+    /// or a version together with `:git`. Unknown keys are ignored, see `unknown_options`.
+    pub fn source(&self) -> Result<ModuleSource, PuppetfileError> {
+        self.located_source(None)
+    }
+
+    /// Like `source`, but errors point at the offending option or the module name
+    pub fn source_at(&self, location: &ModuleLocation) -> Result<ModuleSource, PuppetfileError> {
+        self.located_source(Some(location))
+    }
+
+    fn located_source(&self,
+                      at: Option<&ModuleLocation>)
+                      -> Result<ModuleSource, PuppetfileError> {
+        let invalid_source = |reason: String, location: Option<Location>| -> PuppetfileError {
+            From::from((InvalidSource {
+                            module: self.name.clone(),
+                            location: location.or(at.map(|at| at.name)),
+                        },
+                        format!("invalid source for module '{}': {}", self.name, reason)))
+        };
+        let version_location = || at.and_then(|at| at.version_location(self));
+        let option_location = |key: &str| at.and_then(|at| at.option_location(self, key));
+        let conflict = |first: &str, second: &str| {
+            invalid_source(format!("`:{}` conflicts with `:{}`", second, first),
+                           option_location(second))
+        };
+        let mut version = None;
+        let mut options: Vec<(&str, &Value)> = vec![];
+        for (i, info) in self.info.iter().enumerate() {
+            let location = at.and_then(|at| at.info.get(i).cloned());
+            match *info {
+                ModuleInfo::Version(..) | ModuleInfo::Latest => {
+                    if version.is_some() {
+                        return Err(invalid_source("more than one version given".to_string(),
+                                                       location));
+                    }
+                    version = Some(info);
+                }
+                ModuleInfo::Info(ref key, ref value, _) => {
+                    if options.iter().any(|&(k, _)| k == key) {
+                        return Err(invalid_source(format!("`:{}` given twice", key),
+                                                       location));
+                    }
+                    options.push((key, value));
+                }
+            }
+        }
+        let option = |key: &str| options.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v);
+        let present = |keys: &[&str]| -> Vec<&str> {
+            options.iter().map(|&(k, _)| k).filter(|k| keys.contains(k)).collect()
+        };
+
+        let kinds = present(&["git", "svn", "local"]);
+        if kinds.len() > 1 {
+            return Err(conflict(kinds[0], kinds[1]));
+        }
+        if let (Some(_), Some(&kind)) = (version, kinds.first()) {
+            return Err(invalid_source(format!("a version can not be combined with `:{}`",
+                                                   kind),
+                                           version_location()));
+        }
+        let refs = present(GIT_REFS);
+        if refs.len() > 1 {
+            return Err(conflict(refs[0], refs[1]));
+        }
+        let revisions = present(SVN_REVISIONS);
+        if revisions.len() > 1 {
+            return Err(conflict(revisions[0], revisions[1]));
+        }
+        if let (Some(&git_ref), false) = (refs.first(), kinds == ["git"]) {
+            return Err(invalid_source(format!("`:{}` requires `:git`", git_ref),
+                                           option_location(git_ref)));
+        }
+        if let (Some(&revision), false) = (revisions.first(), kinds == ["svn"]) {
+            return Err(invalid_source(format!("`:{}` requires `:svn`", revision),
+                                           option_location(revision)));
+        }
+
+        let string = |key: &str| -> Result<String, PuppetfileError> {
+            match option(key) {
+                Some(&Value::String(ref value)) => Ok(value.clone()),
+                _ => {
+                    Err(invalid_source(format!("`:{}` expects a string", key),
+                                            option_location(key)))
+                }
+            }
+        };
+        Ok(match kinds.first() {
+            Some(&"git") => {
+                let reference = match refs.first() {
+                    Some(key) => {
+                        Some(try!(GitRef::new(key, option(key).unwrap()).ok_or_else(|| {
+                            invalid_source(format!("`:{}` expects a string", key),
+                                                option_location(key))
+                        })))
+                    }
+                    None => None,
+                };
+                ModuleSource::Git {
+                    url: try!(string("git")),
+                    reference: reference,
+                }
+            }
+            Some(&"svn") => {
+                ModuleSource::Svn {
+                    url: try!(string("svn")),
+                    revision: match revisions.first() {
+                        Some(key) => Some(try!(string(key))),
+                        None => None,
+                    },
+                }
+            }
+            Some(_) => {
+                match option("local") {
+                    Some(&Value::Boolean(true)) => ModuleSource::Local,
+                    Some(&Value::String(ref value)) if value == "true" => ModuleSource::Local,
+                    _ => {
+                        return Err(invalid_source("`:local` expects `true`".to_string(),
+                                                  option_location("local")))
+                    }
+                }
+            }
+            None => {
+                ModuleSource::Forge(match version {
+                    Some(&ModuleInfo::Version(ref req, _)) => ForgeVersion::Req(req.clone()),
+                    Some(_) => ForgeVersion::Latest,
+                    None => ForgeVersion::Any,
+                })
+            }
+        })
+    }
+
+    /// Returns all options r10k does not know about as key value pairs
+    pub fn unknown_options(&self) -> Vec<(&str, &Value)> {
+        self.info
+            .iter()
+            .filter_map(|info| {
+                match *info {
+                    ModuleInfo::Info(ref key, ref value, _) if !KNOWN_OPTIONS.contains(&&key[..]) => {
+                        Some((&key[..], value))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
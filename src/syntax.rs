@@ -0,0 +1,574 @@
+//! Lossless syntax tree of a Puppetfile
+//!
+//! Unlike `Puppetfile` the syntax tree keeps comments, blank lines and the quoting style of
+//! every statement. Printing an unchanged tree reproduces the parsed file byte for byte.
+
+use std::fmt;
+
+use super::{grammar, line_column, Puppetfile, Module, ModuleInfo, HashStyle, Value,
+            PuppetfileError};
+use ErrorKind::{DuplicateDirective, InvalidVersion};
+
+/// A byte range in the source of a Puppetfile
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Span {
+    /// Offset of the first byte
+    pub start: usize,
+    /// Offset after the last byte
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a span from `start` up to, but not including, `end`
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start: start,
+            end: end,
+        }
+    }
+
+    fn shift(self, offset: usize) -> Span {
+        Span::new(self.start + offset, self.end + offset)
+    }
+}
+
+/// A one-based line and column, the column counts characters
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct LineColumn {
+    /// The line
+    pub line: usize,
+    /// The column
+    pub column: usize,
+}
+
+/// A span together with the lines and columns it starts and ends at
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Location {
+    /// The byte offsets
+    pub span: Span,
+    /// Position of the first character
+    pub start: LineColumn,
+    /// Position after the last character
+    pub end: LineColumn,
+}
+
+impl Location {
+    /// Looks up the lines and columns of `span` in `source`
+    pub fn from_span(source: &str, span: Span) -> Location {
+        LineIndex::new(source).location(span)
+    }
+}
+
+/// Whitespace and comments between statements
+#[derive(PartialEq, Clone, Debug)]
+pub struct Trivia {
+    /// Location in the source
+    pub span: Span,
+    /// The whitespace and comments as written
+    pub text: String,
+}
+
+impl Trivia {
+    fn new(source: &str, start: usize, end: usize) -> Trivia {
+        Trivia {
+            span: Span::new(start, end),
+            text: source[start..end].to_string(),
+        }
+    }
+
+    /// Returns all comments including their leading `#`
+    pub fn comments(&self) -> Vec<&str> {
+        self.text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with("#"))
+            .collect()
+    }
+}
+
+/// The lossless syntax tree of a Puppetfile
+#[derive(PartialEq, Clone, Debug)]
+pub struct SyntaxTree {
+    /// All statements in the order they appear
+    pub nodes: Vec<Node>,
+    /// Whitespace and comments after the last statement
+    pub trailing: Trivia,
+}
+
+/// A statement of a Puppetfile together with the trivia around it
+#[derive(PartialEq, Clone, Debug)]
+pub struct Node {
+    /// Whitespace and comment lines in front of the statement
+    pub leading: Trivia,
+    /// The statement as written
+    pub text: String,
+    /// Location of `text` in the source
+    pub span: Span,
+    /// Whitespace and a comment following the statement on the same line
+    pub trailing: Trivia,
+    /// The parsed statement
+    pub kind: NodeKind,
+}
+
+/// The kinds of statements in a Puppetfile
+#[derive(PartialEq, Clone, Debug)]
+pub enum NodeKind {
+    /// `forge 'url'`
+    Forge(Str),
+    /// `moduledir 'path'`
+    Moduledir(Str),
+    /// `mod 'name', options...`
+    Module(ModuleNode),
+}
+
+/// A `mod` statement
+#[derive(PartialEq, Clone, Debug)]
+pub struct ModuleNode {
+    /// The module name
+    pub name: Str,
+    /// Version and hash options in the order they were written
+    pub options: Vec<OptionNode>,
+}
+
+/// A single option of a `mod` statement
+#[derive(PartialEq, Clone, Debug)]
+pub struct OptionNode {
+    /// Location of the whole option
+    pub span: Span,
+    /// The parsed option
+    pub kind: OptionKind,
+}
+
+/// The kinds of options of a `mod` statement
+#[derive(PartialEq, Clone, Debug)]
+pub enum OptionKind {
+    /// A version string
+    Version(Str),
+    /// `:latest`
+    Latest,
+    /// `:key => value` or `key: value`
+    Pair {
+        /// The key, its span does not include the `:` of the Ruby 1.9 syntax
+        key: Ident,
+        /// The value
+        value: ValueNode,
+        /// The syntax the option was written in
+        style: HashStyle,
+    },
+}
+
+/// The value of a hash option
+#[derive(PartialEq, Clone, Debug)]
+pub enum ValueNode {
+    /// A quoted string
+    String(Str),
+    /// A symbol
+    Symbol(Ident),
+    /// A bare `true` or `false`
+    Boolean {
+        /// Location of the keyword
+        span: Span,
+        /// The value
+        value: bool,
+    },
+}
+
+impl ValueNode {
+    /// Location of the value
+    pub fn span(&self) -> Span {
+        match *self {
+            ValueNode::String(ref s) => s.span,
+            ValueNode::Symbol(ref i) => i.span,
+            ValueNode::Boolean { span, .. } => span,
+        }
+    }
+
+    /// The value as used in `ModuleInfo`
+    pub fn value(&self) -> Value {
+        match *self {
+            ValueNode::String(ref s) => Value::String(s.value.clone()),
+            ValueNode::Symbol(ref i) if i.name == "control_branch" => Value::ControlBranch,
+            ValueNode::Symbol(ref i) => Value::Symbol(i.name.clone()),
+            ValueNode::Boolean { value, .. } => Value::Boolean(value),
+        }
+    }
+}
+
+/// Where a module is declared in the source of a Puppetfile, see
+/// `SyntaxTree::module_locations`
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ModuleLocation {
+    /// The whole `mod` statement
+    pub statement: Location,
+    /// The quoted module name
+    pub name: Location,
+    /// The entries of `Module::info` in the same order
+    pub info: Vec<Location>,
+}
+
+impl ModuleLocation {
+    /// Location of the version or `:latest` of the module declared here, if given
+    pub fn version_location(&self, module: &Module) -> Option<Location> {
+        self.info_location(module, |info| {
+            match *info {
+                ModuleInfo::Version(..) | ModuleInfo::Latest => true,
+                ModuleInfo::Info(..) => false,
+            }
+        })
+    }
+
+    /// Location of the option with the given key of the module declared here, if given
+    pub fn option_location(&self, module: &Module, key: &str) -> Option<Location> {
+        self.info_location(module, |info| {
+            match *info {
+                ModuleInfo::Info(ref k, _, _) => k == key,
+                ModuleInfo::Version(..) | ModuleInfo::Latest => false,
+            }
+        })
+    }
+
+    fn info_location<F>(&self, module: &Module, f: F) -> Option<Location>
+        where F: Fn(&ModuleInfo) -> bool
+    {
+        module.info.iter().position(f).and_then(|index| self.info.get(index).cloned())
+    }
+}
+
+/// A quoted string
+#[derive(PartialEq, Clone, Debug)]
+pub struct Str {
+    /// Location including the quotes
+    pub span: Span,
+    /// The unescaped content
+    pub value: String,
+    /// The quote character, `'` or `"`
+    pub quote: char,
+}
+
+/// A symbol or the key of a Ruby 1.9 style option
+#[derive(PartialEq, Clone, Debug)]
+pub struct Ident {
+    /// Location as written, including a leading `:`
+    pub span: Span,
+    /// The name without any `:`
+    pub name: String,
+}
+
+impl SyntaxTree {
+    /// Parses the contents of a Puppetfile into a syntax tree
+    pub fn parse(contents: &str) -> Result<SyntaxTree, PuppetfileError> {
+        let statements = try!(grammar::parse(contents));
+        Ok(SyntaxTree::build(contents, statements))
+    }
+
+    /// Parses as much of a Puppetfile as possible and returns every syntax error
+    ///
+    /// After an error parsing continues at the next `mod`, `forge` or `moduledir` statement.
+    /// Text that could not be parsed ends up in the trivia, so the tree still prints the
+    /// source unchanged.
+    pub fn parse_recovering(contents: &str) -> (SyntaxTree, Vec<PuppetfileError>) {
+        if let Ok(statements) = grammar::parse(contents) {
+            return (SyntaxTree::build(contents, statements), vec![]);
+        }
+        let mut statements = vec![];
+        let mut errors = vec![];
+        let starts = statement_starts(contents);
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).cloned().unwrap_or(contents.len());
+            match grammar::parse(&contents[start..end]) {
+                Ok(chunk) => {
+                    statements.extend(chunk.into_iter().map(|(span, mut kind)| {
+                        kind.shift(start);
+                        (span.shift(start), kind)
+                    }))
+                }
+                Err(err) => {
+                    let offset = start + err.offset;
+                    let (line, column) = line_column(contents, offset);
+                    errors.push(From::from(grammar::ParseError {
+                        line: line,
+                        column: column,
+                        offset: offset,
+                        expected: err.expected,
+                    }))
+                }
+            }
+        }
+        (SyntaxTree::build(contents, statements), errors)
+    }
+
+    fn build(contents: &str, statements: Vec<(Span, NodeKind)>) -> SyntaxTree {
+        let mut nodes = vec![];
+        let mut pos = 0;
+        for (span, kind) in statements {
+            let trailing_end = trailing_trivia_end(contents, span.end);
+            nodes.push(Node {
+                leading: Trivia::new(contents, pos, span.start),
+                text: contents[span.start..span.end].to_string(),
+                span: span,
+                trailing: Trivia::new(contents, span.end, trailing_end),
+                kind: kind,
+            });
+            pos = trailing_end;
+        }
+
+        SyntaxTree {
+            nodes: nodes,
+            trailing: Trivia::new(contents, pos, contents.len()),
+        }
+    }
+
+    /// Builds the `Puppetfile` described by the syntax tree
+    pub fn to_puppetfile(&self) -> Result<Puppetfile, PuppetfileError> {
+        let (puppetfile, errors) = self.build_puppetfile(false);
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(puppetfile),
+        }
+    }
+
+    /// Builds the `Puppetfile` from all valid statements and returns the errors of the others
+    pub fn to_puppetfile_recovering(&self) -> (Puppetfile, Vec<PuppetfileError>) {
+        self.build_puppetfile(true)
+    }
+
+    fn build_puppetfile(&self, recover: bool) -> (Puppetfile, Vec<PuppetfileError>) {
+        let source = self.to_string();
+        let lines = LineIndex::new(&source);
+        let mut errors = vec![];
+        let mut puppetfile = Puppetfile {
+            forge: None,
+            moduledir: None,
+            modules: vec![],
+        };
+        for node in self.nodes.iter() {
+            let result = match node.kind {
+                NodeKind::Forge(ref url) => {
+                    if puppetfile.forge.is_some() {
+                        Err(duplicate_directive("forge", &source, node.span.start))
+                    } else {
+                        puppetfile.forge = Some(url.value.clone());
+                        Ok(())
+                    }
+                }
+                NodeKind::Moduledir(ref path) => {
+                    if puppetfile.moduledir.is_some() {
+                        Err(duplicate_directive("moduledir", &source, node.span.start))
+                    } else {
+                        puppetfile.moduledir = Some(path.value.clone());
+                        Ok(())
+                    }
+                }
+                NodeKind::Module(ref module) => {
+                    module.module(&lines).map(|module| puppetfile.modules.push(module))
+                }
+            };
+            if let Err(err) = result {
+                errors.push(err);
+                if !recover {
+                    break;
+                }
+            }
+        }
+        (puppetfile, errors)
+    }
+
+    /// Builds all modules, unlike `to_puppetfile` ignoring errors in `forge` and `moduledir`
+    pub fn modules(&self) -> Result<Vec<Module>, PuppetfileError> {
+        let source = self.to_string();
+        let lines = LineIndex::new(&source);
+        let mut modules = vec![];
+        for node in self.nodes.iter() {
+            if let NodeKind::Module(ref module) = node.kind {
+                modules.push(try!(module.module(&lines)));
+            }
+        }
+        Ok(modules)
+    }
+
+    /// Where every module is declared, in the order of `modules`
+    pub fn module_locations(&self) -> Vec<ModuleLocation> {
+        let source = self.to_string();
+        let lines = LineIndex::new(&source);
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                match node.kind {
+                    NodeKind::Module(ref module) => Some(module.location(&lines, node.span)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in self.nodes.iter() {
+            try!(write!(f, "{}", node));
+        }
+        write!(f, "{}", self.trailing.text)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading.text, self.text, self.trailing.text)
+    }
+}
+
+impl NodeKind {
+    /// Moves all spans by `offset`, for statements parsed from a part of the source
+    fn shift(&mut self, offset: usize) {
+        match *self {
+            NodeKind::Forge(ref mut s) | NodeKind::Moduledir(ref mut s) => {
+                s.span = s.span.shift(offset)
+            }
+            NodeKind::Module(ref mut module) => {
+                module.name.span = module.name.span.shift(offset);
+                for option in module.options.iter_mut() {
+                    option.span = option.span.shift(offset);
+                    match option.kind {
+                        OptionKind::Version(ref mut s) => s.span = s.span.shift(offset),
+                        OptionKind::Latest => (),
+                        OptionKind::Pair { ref mut key, ref mut value, .. } => {
+                            key.span = key.span.shift(offset);
+                            match *value {
+                                ValueNode::String(ref mut s) => s.span = s.span.shift(offset),
+                                ValueNode::Symbol(ref mut i) => i.span = i.span.shift(offset),
+                                ValueNode::Boolean { ref mut span, .. } => {
+                                    *span = span.shift(offset)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ModuleNode {
+    fn module(&self, lines: &LineIndex) -> Result<Module, PuppetfileError> {
+        let mut info = vec![];
+        for option in self.options.iter() {
+            info.push(match option.kind {
+                OptionKind::Version(ref version) => try!(version_info(version, lines)),
+                OptionKind::Latest => ModuleInfo::Latest,
+                OptionKind::Pair { ref key, ref value, style } => {
+                    ModuleInfo::Info(key.name.clone(), value.value(), style)
+                }
+            });
+        }
+        Ok(Module {
+            name: self.name.value.clone(),
+            info: info,
+        })
+    }
+
+    fn location(&self, lines: &LineIndex, statement: Span) -> ModuleLocation {
+        ModuleLocation {
+            statement: lines.location(statement),
+            name: lines.location(self.name.span),
+            info: self.options.iter().map(|option| lines.location(option.span)).collect(),
+        }
+    }
+}
+
+fn version_info(version: &Str, lines: &LineIndex) -> Result<ModuleInfo, PuppetfileError> {
+    let raw = &version.value;
+    match ModuleInfo::version(raw) {
+        Ok(info) => Ok(info),
+        Err(_) => {
+            let location = lines.location(version.span);
+            let desc = format!("invalid version '{}' at {}:{}",
+                               raw,
+                               location.start.line,
+                               location.start.column);
+            Err(From::from((InvalidVersion {
+                                version: raw.clone(),
+                                location: location,
+                            },
+                            desc)))
+        }
+    }
+}
+
+fn duplicate_directive(directive: &str, source: &str, pos: usize) -> PuppetfileError {
+    let (line, column) = line_column(source, pos);
+    From::from((DuplicateDirective {
+                    directive: directive.to_string(),
+                    line: line,
+                    column: column,
+                },
+                format!("`{}` declared a second time at {}:{}", directive, line, column)))
+}
+
+/// Returns the offsets of all lines starting with a statement keyword, always including 0
+fn statement_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut line_start = 0;
+    for line in source.split('\n') {
+        let code = line.trim_left();
+        let is_statement = ["mod", "forge", "moduledir"].iter().any(|keyword| {
+            code.starts_with(keyword) &&
+            code[keyword.len()..].starts_with(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+        });
+        if is_statement && line_start > 0 {
+            starts.push(line_start);
+        }
+        line_start += line.len() + 1;
+    }
+    starts
+}
+
+/// Start offsets of all lines for looking up many positions in the same source
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> LineIndex<'a> {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex {
+            source: source,
+            starts: starts,
+        }
+    }
+
+    fn line_column(&self, pos: usize) -> LineColumn {
+        let line = match self.starts.binary_search(&pos) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        LineColumn {
+            line: line + 1,
+            column: self.source[self.starts[line]..pos].chars().count() + 1,
+        }
+    }
+
+    fn location(&self, span: Span) -> Location {
+        Location {
+            span: span,
+            start: self.line_column(span.start),
+            end: self.line_column(span.end),
+        }
+    }
+}
+
+/// Returns the end of the whitespace and comment on the line a statement ends on
+fn trailing_trivia_end(source: &str, end: usize) -> usize {
+    let rest = &source[end..];
+    let blank = rest.len() - rest.trim_left_matches(|c| c == ' ' || c == '\t').len();
+    let after_blank = &rest[blank..];
+    if after_blank.starts_with("#") {
+        end + blank + after_blank.find(&['\r', '\n'][..]).unwrap_or(after_blank.len())
+    } else if after_blank.is_empty() || after_blank.starts_with("\n") ||
+              after_blank.starts_with("\r") {
+        end + blank
+    } else {
+        end
+    }
+}
@@ -1,12 +1,33 @@
-use super::{Puppetfile, Module, ModuleInfo};
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use super::{Puppetfile, Module, ModuleInfo, ModuleSource, ForgeVersion, GitRef, Value,
+            HashStyle, ErrorKind, SyntaxTree, Renderer, ModuleName,
+            FormatStyle, line_diff, SortOrder,
+            LintConfig, Severity, CollisionKind, ForgeClient,
+            FakeTransport, CachingTransport, ForgeCredentials, ForgeAuth, Token,
+            RetryPolicy, Transport, Request, Response, PuppetfileError};
+use super::forge::{ReleaseFilter, api_url};
+use super::lint;
+use super::syntax::{NodeKind, OptionKind};
 use semver::{self, VersionReq};
+use quickcheck::{quickcheck, Arbitrary, Gen};
 
 #[test]
 fn empty_file() {
     let puppetfile = Puppetfile::parse(r##"forge "https://forge.puppetlabs.com""##);
     assert!(puppetfile.is_ok());
     let parsed = puppetfile.unwrap();
-    assert_eq!("https://forge.puppetlabs.com", parsed.forge);
+    assert_eq!(Some("https://forge.puppetlabs.com".to_string()), parsed.forge);
     let expected: Vec<Module> = vec![];
     assert_eq!(expected, parsed.modules);
 }
@@ -20,7 +41,7 @@ mod 'mayflower/php'
     assert!(puppetfile.is_ok());
 
     let parsed = puppetfile.unwrap();
-    assert_eq!("https://forge.puppetlabs.com", parsed.forge);
+    assert_eq!(Some("https://forge.puppetlabs.com".to_string()), parsed.forge);
     assert_eq!(Module {
                    name: "mayflower/php".to_string(),
                    info: vec![],
@@ -37,10 +58,11 @@ mod 'mayflower/php', '1.0.1'
     assert!(puppetfile.is_ok());
 
     let parsed = puppetfile.unwrap();
-    assert_eq!("https://forge.puppetlabs.com", parsed.forge);
+    assert_eq!(Some("https://forge.puppetlabs.com".to_string()), parsed.forge);
     assert_eq!(Module {
                    name: "mayflower/php".to_string(),
-                   info: vec![ModuleInfo::Version(VersionReq::parse("= 1.0.1").unwrap())],
+                   info: vec![ModuleInfo::Version(VersionReq::parse("= 1.0.1").unwrap(),
+                                                  "1.0.1".to_string())],
                },
                parsed.modules[0]);
 }
@@ -55,23 +77,99 @@ mod 'mayflower/php',
     assert!(puppetfile.is_ok());
 
     let parsed = puppetfile.unwrap();
-    assert_eq!("https://forge.puppetlabs.com", parsed.forge);
+    assert_eq!(Some("https://forge.puppetlabs.com".to_string()), parsed.forge);
     assert_eq!(Module {
                    name: "mayflower/php".to_string(),
                    info: vec![ModuleInfo::Info("git".to_string(),
-                                               "git://github.com/Mayflower/puppet-php.git"
-                                                   .to_string())],
+                                               Value::String("git://github.com/Mayflower/puppet-php.git"
+                                                                 .to_string()),
+                                               HashStyle::HashRocket)],
                },
                parsed.modules[0]);
 }
 
+#[test]
+fn symbols() {
+    let puppetfile = Puppetfile::parse(r##"
+mod 'puppetlabs/stdlib', :latest
+mod 'site',
+  :git => 'https://git.example.com/site.git',
+  :branch => :control_branch,
+  :default_branch => 'main'
+    "##)
+                         .unwrap();
+    assert_eq!(vec![ModuleInfo::Latest], puppetfile.modules[0].info);
+    assert_eq!(ModuleSource::Forge(ForgeVersion::Latest),
+               puppetfile.modules[0].source().unwrap());
+    assert_eq!(ModuleInfo::Info("branch".to_string(),
+                                Value::ControlBranch,
+                                HashStyle::HashRocket),
+               puppetfile.modules[1].info[1]);
+    assert_eq!(ModuleSource::Git {
+                   url: "https://git.example.com/site.git".to_string(),
+                   reference: Some(GitRef::ControlBranch),
+               },
+               puppetfile.modules[1].source().unwrap());
+    assert_eq!("mod 'puppetlabs/stdlib', :latest", format!("{}", puppetfile.modules[0]));
+    assert_eq!("mod 'site',
+  :git => 'https://git.example.com/site.git',
+  :branch => :control_branch,
+  :default_branch => 'main'",
+               format!("{}", puppetfile.modules[1]));
+
+    let module = Puppetfile::parse("mod 'site', :git => :foo").unwrap().modules.remove(0);
+    assert_eq!(ModuleInfo::Info("git".to_string(),
+                                Value::Symbol("foo".to_string()),
+                                HashStyle::HashRocket),
+               module.info[0]);
+    assert!(module.source().is_err());
+}
+
+#[test]
+fn ruby19_hash_syntax() {
+    let mut puppetfile = Puppetfile::parse(r##"
+mod 'apache', git: 'https://github.com/puppetlabs/puppetlabs-apache.git',
+  :tag => 'v1.2.0'
+mod 'site',
+  git: 'https://git.example.com/site.git', branch: :control_branch
+    "##)
+                             .unwrap();
+    assert_eq!(vec![ModuleInfo::Info("git".to_string(),
+                                     Value::String("https://github.com/puppetlabs/puppetlabs-apache.git"
+                                                       .to_string()),
+                                     HashStyle::Ruby19),
+                    ModuleInfo::Info("tag".to_string(),
+                                     Value::String("v1.2.0".to_string()),
+                                     HashStyle::HashRocket)],
+               puppetfile.modules[0].info);
+    assert_eq!(ModuleSource::Git {
+                   url: "https://git.example.com/site.git".to_string(),
+                   reference: Some(GitRef::ControlBranch),
+               },
+               puppetfile.modules[1].source().unwrap());
+    assert_eq!("mod 'apache',
+  git: 'https://github.com/puppetlabs/puppetlabs-apache.git',
+  :tag => 'v1.2.0'",
+               format!("{}", puppetfile.modules[0]));
+
+    puppetfile.set_hash_style(HashStyle::Ruby19);
+    assert_eq!("mod 'site',
+  git: 'https://git.example.com/site.git',
+  branch: :control_branch",
+               format!("{}", puppetfile.modules[1]));
+}
+
 #[test]
 fn format() {
-    let version = ModuleInfo::Version(VersionReq::parse("= 1.0.0").unwrap());
-    assert_eq!("= 1.0.0".to_string(), format!("{}", version));
+    let version = ModuleInfo::version("1.0.0").unwrap();
+    assert_eq!(ModuleInfo::Version(VersionReq::parse("= 1.0.0").unwrap(), "1.0.0".to_string()),
+               version);
+    assert_eq!("1.0.0".to_string(), format!("{}", version));
 
     let mod_info = ModuleInfo::Info("git".to_string(),
-                                    "git://github.com/Mayflower/puppet-php.git".to_string());
+                                    Value::String("git://github.com/Mayflower/puppet-php.git"
+                                                      .to_string()),
+                                    HashStyle::HashRocket);
     assert_eq!(":git => 'git://github.com/Mayflower/puppet-php.git'",
                format!("{}", mod_info));
 
@@ -79,33 +177,853 @@ fn format() {
         name: "mayflower/php".to_string(),
         info: vec![version, mod_info],
     };
-    assert_eq!("mod 'mayflower/php', '= 1.0.0',
+    assert_eq!("mod 'mayflower/php', '1.0.0',
   :git => \
                 'git://github.com/Mayflower/puppet-php.git'",
                format!("{}", module));
 
     let puppetfile = Puppetfile {
-        forge: "https://forge.puppetlabs.com".to_string(),
+        forge: Some("https://forge.puppetlabs.com".to_string()),
+        moduledir: None,
         modules: vec![module],
     };
     assert_eq!("forge 'https://forge.puppetlabs.com'
 
 
-mod 'mayflower/php', '= 1.0.0',
+mod 'mayflower/php', '1.0.0',
   :git => \
                 'git://github.com/Mayflower/puppet-php.git'
 ",
                format!("{}", puppetfile));
 }
 
+#[derive(Clone, Debug)]
+struct ArbitraryPuppetfile(Puppetfile);
+
+impl Arbitrary for ArbitraryPuppetfile {
+    fn arbitrary<G: Gen>(g: &mut G) -> ArbitraryPuppetfile {
+        let modules = (0..below(g, 6)).map(|_| arbitrary_module(g)).collect();
+        ArbitraryPuppetfile(Puppetfile {
+            forge: Arbitrary::arbitrary(g),
+            moduledir: Arbitrary::arbitrary(g),
+            modules: modules,
+        })
+    }
+}
+
+fn below<G: Gen>(g: &mut G, n: usize) -> usize {
+    usize::arbitrary(g) % n
+}
+
+fn arbitrary_word<G: Gen>(g: &mut G) -> String {
+    (0..below(g, 8) + 1).map(|_| (b'a' + below(g, 26) as u8) as char).collect()
+}
+
+fn arbitrary_module<G: Gen>(g: &mut G) -> Module {
+    let mut info = vec![];
+    match below(g, 3) {
+        0 => (),
+        1 => info.push(ModuleInfo::Latest),
+        _ => {
+            let version = format!("{}{}.{}.{}",
+                                  ["", "=", ">= ", "< ", "~", "^"][below(g, 6)],
+                                  u8::arbitrary(g),
+                                  u8::arbitrary(g),
+                                  u8::arbitrary(g));
+            info.push(ModuleInfo::version(&version).unwrap());
+        }
+    }
+    for _ in 0..below(g, 4) {
+        let value = match below(g, 4) {
+            0 => Value::ControlBranch,
+            1 => Value::Symbol(arbitrary_word(g)),
+            2 => Value::Boolean(bool::arbitrary(g)),
+            _ => Value::String(Arbitrary::arbitrary(g)),
+        };
+        let style = if bool::arbitrary(g) {
+            HashStyle::Ruby19
+        } else {
+            HashStyle::HashRocket
+        };
+        info.push(ModuleInfo::Info(arbitrary_word(g), value, style));
+    }
+    Module {
+        name: Arbitrary::arbitrary(g),
+        info: info,
+    }
+}
+
+#[test]
+fn display_round_trip() {
+    fn round_trip(puppetfile: ArbitraryPuppetfile) -> bool {
+        let ArbitraryPuppetfile(puppetfile) = puppetfile;
+        Puppetfile::parse(&puppetfile.to_string()).ok() == Some(puppetfile)
+    }
+    quickcheck(round_trip as fn(ArbitraryPuppetfile) -> bool);
+
+    let puppetfile = Puppetfile::parse(r##"mod 'it\'s', '>= 1.0.0', :path => 'C:\\modules'
+mod 'pinned', '1.0.0'
+"##)
+                         .unwrap();
+    assert_eq!(r##"
+mod 'it\'s', '>= 1.0.0',
+  :path => 'C:\\modules'
+
+mod 'pinned', '1.0.0'
+"##,
+               puppetfile.to_string());
+}
+
+#[test]
+fn moduledir() {
+    let puppetfile = Puppetfile::parse(r##"moduledir 'site-modules'
+forge "https://forge.puppetlabs.com"
+
+mod 'mayflower/php', '1.0.1'
+    "##);
+    assert!(puppetfile.is_ok());
+
+    let parsed = puppetfile.unwrap();
+    assert_eq!(Some("site-modules".to_string()), parsed.moduledir);
+    assert_eq!("site-modules", parsed.moduledir());
+    assert_eq!(Path::new("site-modules/php"),
+               parsed.install_path(&parsed.modules[0]));
+    assert_eq!("forge 'https://forge.puppetlabs.com'
+moduledir 'site-modules'
+
+
+mod 'mayflower/php', '1.0.1'
+",
+               format!("{}", parsed));
+
+    let puppetfile = Puppetfile::parse(r##"forge "https://forge.puppetlabs.com"
+moduledir 'site-modules'
+    "##);
+    assert_eq!(Some("site-modules".to_string()), puppetfile.unwrap().moduledir);
+}
+
+#[test]
+fn default_moduledir() {
+    let puppetfile = Puppetfile::parse(r##"forge "https://forge.puppetlabs.com"
+
+mod 'apache',
+  :git => 'https://github.com/puppetlabs/puppetlabs-apache.git'
+    "##)
+                         .unwrap();
+    assert_eq!(None, puppetfile.moduledir);
+    assert_eq!(Path::new("modules/apache"),
+               puppetfile.install_path(&puppetfile.modules[0]));
+}
+
+#[test]
+fn invalid_version() {
+    let err = Puppetfile::parse(r##"forge "https://forge.puppetlabs.com"
+
+mod 'mayflower/php', '1.0.1'
+mod 'a/b', '1.x.y.z'
+    "##)
+                  .unwrap_err();
+    match err.kind {
+        ErrorKind::InvalidVersion { ref version, location } => {
+            assert_eq!("1.x.y.z", version);
+            assert_eq!((4, 12), (location.start.line, location.start.column));
+            assert_eq!((4, 21), (location.end.line, location.end.column));
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!("invalid version '1.x.y.z' at 4:12", err.desc);
+}
+
+#[test]
+fn no_forge() {
+    let puppetfile = Puppetfile::parse(r##"
+mod 'apache',
+  :git => 'https://github.com/puppetlabs/puppetlabs-apache.git'
+    "##)
+                         .unwrap();
+    assert_eq!(None, puppetfile.forge);
+    assert_eq!("https://forgeapi.puppetlabs.com", puppetfile.forge_url());
+    assert_eq!(1, puppetfile.modules.len());
+    assert_eq!("https://forgeapi.puppetlabs.com",
+               api_url("https://forge.puppetlabs.com/"));
+    assert_eq!("https://forge.example.com", api_url("https://forge.example.com"));
+}
+
+#[test]
+fn forge_after_modules() {
+    let puppetfile = Puppetfile::parse(r##"mod 'mayflower/php', '1.0.1'
+forge "https://forge.example.com"
+mod 'puppetlabs/stdlib'
+    "##)
+                         .unwrap();
+    assert_eq!(Some("https://forge.example.com".to_string()), puppetfile.forge);
+    assert_eq!(2, puppetfile.modules.len());
+}
+
+#[test]
+fn duplicate_forge() {
+    let err = Puppetfile::parse(r##"forge "https://forge.puppetlabs.com"
+mod 'mayflower/php', '1.0.1'
+  forge "https://forge.example.com"
+    "##)
+                  .unwrap_err();
+    match err.kind {
+        ErrorKind::DuplicateDirective { ref directive, line, column } => {
+            assert_eq!("forge", directive);
+            assert_eq!((3, 3), (line, column));
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn source() {
+    let puppetfile = Puppetfile::parse(r##"
+mod 'mayflower/php', '1.0.1'
+mod 'puppetlabs/stdlib'
+mod 'apache',
+  :git => 'https://github.com/puppetlabs/puppetlabs-apache.git',
+  :tag => 'v1.2.0',
+  :future_option => 'yes'
+mod 'nginx',
+  :svn => 'https://svn.example.com/nginx',
+  :rev => '1234'
+mod 'site',
+  :local => 'true'
+    "##)
+                         .unwrap();
+    let sources = puppetfile.modules
+                            .iter()
+                            .map(|module| module.source().unwrap())
+                            .collect::<Vec<_>>();
+    assert_eq!(vec![ModuleSource::Forge(ForgeVersion::Req(VersionReq::parse("= 1.0.1")
+                                                              .unwrap())),
+                    ModuleSource::Forge(ForgeVersion::Any),
+                    ModuleSource::Git {
+                        url: "https://github.com/puppetlabs/puppetlabs-apache.git".to_string(),
+                        reference: Some(GitRef::Tag("v1.2.0".to_string())),
+                    },
+                    ModuleSource::Svn {
+                        url: "https://svn.example.com/nginx".to_string(),
+                        revision: Some("1234".to_string()),
+                    },
+                    ModuleSource::Local],
+               sources);
+    assert_eq!(vec![("future_option", &Value::String("yes".to_string()))],
+               puppetfile.modules[2].unknown_options());
+}
+
+#[test]
+fn invalid_source() {
+    let tree = SyntaxTree::parse(r##"
+mod 'apache',
+  :git => 'https://github.com/puppetlabs/puppetlabs-apache.git',
+  :tag => 'v1.2.0',
+  :branch => 'main'
+mod 'mayflower/php', '1.0.1',
+  :git => 'https://github.com/Mayflower/puppet-php.git'
+mod 'puppetlabs/stdlib',
+  :branch => 'main'
+    "##)
+                   .unwrap();
+    let puppetfile = tree.to_puppetfile().unwrap();
+    let mut lines = vec![];
+    for (module, location) in puppetfile.modules.iter().zip(tree.module_locations()) {
+        match module.source_at(&location).unwrap_err().kind {
+            ErrorKind::InvalidSource { module: ref name, location: Some(location) } => {
+                assert_eq!(&module.name, name);
+                lines.push(location.start.line);
+            }
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+    assert_eq!(vec![5, 6, 9], lines);
+    let err = puppetfile.modules[0].source().unwrap_err();
+    assert_eq!("invalid source for module 'apache': `:branch` conflicts with `:tag`",
+               err.desc);
+    match err.kind {
+        ErrorKind::InvalidSource { location: None, .. } => (),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn local_source() {
+    let contents = "mod 'site', :local => true\nmod 'profile', local: true\n";
+    let puppetfile = Puppetfile::parse(contents).unwrap();
+    assert_eq!(ModuleInfo::Info("local".to_string(),
+                                Value::Boolean(true),
+                                HashStyle::HashRocket),
+               puppetfile.modules[0].info[0]);
+    for module in puppetfile.modules.iter() {
+        assert_eq!(ModuleSource::Local, module.source().unwrap());
+    }
+    assert_eq!("mod 'site',\n  :local => true", puppetfile.modules[0].to_string());
+    let formatted = SyntaxTree::parse(contents).unwrap().format(&FormatStyle::default());
+    assert_eq!(puppetfile, Puppetfile::parse(&formatted).unwrap());
+    assert!(Puppetfile::parse("mod 'site', :local => trueish").is_err());
+
+    for &value in &["false", "'false'", ":yes"] {
+        let contents = format!("mod 'site', :local => {}", value);
+        let tree = SyntaxTree::parse(&contents).unwrap();
+        let module = &tree.modules().unwrap()[0];
+        match module.source_at(&tree.module_locations()[0]).unwrap_err().kind {
+            ErrorKind::InvalidSource { location: Some(location), .. } => {
+                assert_eq!(13, location.start.column)
+            }
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
+
+const CONTROL_REPO: &'static str = r##"# Puppetfile of the control repository
+moduledir 'site-modules'
+forge "https://forge.puppetlabs.com"
+
+# pinned, see OPS-123
+mod 'puppetlabs/stdlib',   '4.9.0' # do not bump
+mod "puppetlabs/apache", :latest
+
+mod 'nginx',
+  :git    => 'https://github.com/voxpupuli/puppet-nginx.git', # fork
+  # the tag we tested
+  tag: "v0.6.0"
+mod 'site', :local => 'true'	# kept locally
+
+"##;
+
+#[test]
+fn syntax_tree_is_lossless() {
+    let tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    assert_eq!(CONTROL_REPO, tree.to_string());
+    assert_eq!(6, tree.nodes.len());
+    for node in tree.nodes.iter() {
+        assert_eq!(&CONTROL_REPO[node.span.start..node.span.end], node.text);
+    }
+
+    for contents in &["", "\n\n", "mod 'a'", "  forge 'x'\r\nmod 'a'  # c\r\n", "mod 'a' mod 'b'"] {
+        assert_eq!(*contents, SyntaxTree::parse(contents).unwrap().to_string());
+    }
+}
+
+#[test]
+fn syntax_tree_trivia() {
+    let tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    assert_eq!(vec!["# Puppetfile of the control repository"],
+               tree.nodes[0].leading.comments());
+    assert_eq!(vec!["# pinned, see OPS-123"], tree.nodes[2].leading.comments());
+    assert_eq!(" # do not bump", tree.nodes[2].trailing.text);
+    assert_eq!("", tree.nodes[3].trailing.text);
+    assert_eq!("\t# kept locally", tree.nodes[5].trailing.text);
+    assert_eq!("\n\n", tree.trailing.text);
+
+    match tree.nodes[4].kind {
+        NodeKind::Module(ref module) => {
+            assert_eq!("nginx", module.name.value);
+            assert_eq!('\'', module.name.quote);
+            assert_eq!(2, module.options.len());
+            match module.options[1].kind {
+                OptionKind::Pair { ref key, ref value, style } => {
+                    assert_eq!("tag", key.name);
+                    assert_eq!("tag", &CONTROL_REPO[key.span.start..key.span.end]);
+                    assert_eq!("\"v0.6.0\"",
+                               &CONTROL_REPO[value.span().start..value.span().end]);
+                    assert_eq!(HashStyle::Ruby19, style);
+                }
+                ref kind => panic!("unexpected option {:?}", kind),
+            }
+        }
+        ref kind => panic!("unexpected node {:?}", kind),
+    }
+
+    let puppetfile = tree.to_puppetfile().unwrap();
+    assert_eq!(Some("site-modules".to_string()), puppetfile.moduledir);
+    assert_eq!(4, puppetfile.modules.len());
+}
+
+#[test]
+fn recovering_parse() {
+    let contents = r##"forge 'https://forge.puppetlabs.com'
+
+mod 'puppetlabs/stdlib', '4.9.0'
+mod 'puppetlabs/apache', => '1.2.0'
+mod 'puppetlabs/concat', '1.x.y.z'
+# comment
+mod 'nginx',
+  :git => 'https://github.com/voxpupuli/puppet-nginx.git',
+mod 'site', :local => 'true'
+forge 'https://forge.example.com'
+"##;
+    assert!(Puppetfile::parse(contents).is_err());
+    let (tree, errors) = SyntaxTree::parse_recovering(contents);
+    assert_eq!(contents, tree.to_string());
+    assert_eq!(vec![(4, 26), (9, 1)],
+               errors.iter()
+                     .map(|err| {
+                         match err.kind {
+                             ErrorKind::ParseError(ref err) => (err.line, err.column),
+                             ref kind => panic!("unexpected error {:?}", kind),
+                         }
+                     })
+                     .collect::<Vec<_>>());
+
+    let (puppetfile, errors) = Puppetfile::parse_recovering(contents);
+    assert_eq!(4, errors.len());
+    match errors[2].kind {
+        ErrorKind::InvalidVersion { location, .. } => {
+            assert_eq!((5, 26), (location.start.line, location.start.column))
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    match errors[3].kind {
+        ErrorKind::DuplicateDirective { line: 10, column: 1, .. } => (),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(Some("https://forge.puppetlabs.com".to_string()), puppetfile.forge);
+    assert_eq!(vec!["puppetlabs/stdlib", "site"],
+               puppetfile.modules.iter().map(|m| &m.name[..]).collect::<Vec<_>>());
+
+    let (_, errors) = Puppetfile::parse_recovering(CONTROL_REPO);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn render_errors() {
+    let contents = "forge 'https://forge.puppetlabs.com'\n\
+                    mod 'nginx', '1.0' :git => 'https://github.com/voxpupuli/puppet-nginx.git'\n\
+                    mod 'site',\n\
+                    \t:local => 'true'\n\
+                    \t:install_path => 'site'\n\
+                    mod 'stdlib', '4.x.y'\n";
+    let (_, errors) = Puppetfile::parse_recovering(contents);
+    let renderer = Renderer::new("Puppetfile", contents);
+    assert_eq!(r##"error: expected one of `,`, `forge`, `mod`, `moduledir`
+ --> Puppetfile:2:20
+  |
+2 | mod 'nginx', '1.0' :git => 'https://github.com/voxpupuli/puppet-nginx.git'
+  |                    ^^^^
+  = help: did you mean `mod 'nginx', '1.0', :git ...`?
+
+error: expected one of `,`, `forge`, `mod`, `moduledir`
+ --> Puppetfile:5:2
+  |
+5 | 	:install_path => 'site'
+  | 	^^^^^^^^^^^^^
+  = help: the previous line might be missing a `,` at its end
+
+error: invalid version '4.x.y'
+ --> Puppetfile:6:15
+  |
+6 | mod 'stdlib', '4.x.y'
+  |               ^^^^^^^
+"##,
+               renderer.render_all(&errors));
+
+    let module = Module {
+        name: "nginx".to_string(),
+        info: vec![ModuleInfo::Info("tag".to_string(),
+                                    Value::String("v1".to_string()),
+                                    HashStyle::HashRocket)],
+    };
+    let err = module.source().unwrap_err();
+    assert_eq!("error: invalid source for module 'nginx': `:tag` requires `:git`\n",
+               renderer.render(&err));
+
+    let contents = "mod 'nginx' '1.0'\nmod 'site', :local => 'true', :tag => 'v1'\n";
+    let renderer = Renderer::new("Puppetfile", contents);
+    let err = Puppetfile::parse(contents).unwrap_err();
+    assert_eq!(r##"error: expected one of `,`, `forge`, `mod`, `moduledir`
+ --> Puppetfile:1:13
+  |
+1 | mod 'nginx' '1.0'
+  |             ^^^^^
+  = help: did you mean `mod 'nginx', '1.0'`?
+"##,
+               renderer.render(&err));
+    for contents in &["mod 'c' x\n", "mod 'c' :\n", "mod 'c' x::y\n"] {
+        let err = Puppetfile::parse(contents).unwrap_err();
+        assert!(!Renderer::new("Puppetfile", contents).render(&err).contains("help"));
+    }
+    let contents = "mod 'c' tag: 'v1'\n";
+    let err = Puppetfile::parse(contents).unwrap_err();
+    assert!(Renderer::new("Puppetfile", contents)
+                .render(&err)
+                .contains("did you mean `mod 'c', tag: ...`?"));
+    let contents = "mod 'nginx' '1.0'\nmod 'site', :local => 'true', :tag => 'v1'\n";
+    let tree = SyntaxTree::parse(&contents[18..]).unwrap();
+    let module = &tree.modules().unwrap()[0];
+    let renderer = Renderer::new("Puppetfile", &contents[18..]);
+    assert_eq!(r##"error: invalid source for module 'site': `:tag` requires `:git`
+ --> Puppetfile:1:31
+  |
+1 | mod 'site', :local => 'true', :tag => 'v1'
+  |                               ^^^^
+"##,
+               renderer.render(&module.source_at(&tree.module_locations()[0])
+                                      .unwrap_err()));
+}
+
+#[test]
+fn module_locations() {
+    let tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    let modules = tree.to_puppetfile().unwrap().modules;
+    let locations = tree.module_locations();
+    assert_eq!(4, locations.len());
+    assert_eq!("puppetlabs/stdlib", modules[0].name);
+    let version = locations[0].version_location(&modules[0]).unwrap();
+    assert_eq!("'4.9.0'", &CONTROL_REPO[version.span.start..version.span.end]);
+    assert_eq!((6, 28), (version.start.line, version.start.column));
+    assert_eq!(1, locations[1].info.len());
+
+    let nginx = &locations[2];
+    assert_eq!((9, 1), (nginx.statement.start.line, nginx.statement.start.column));
+    assert_eq!((12, 16), (nginx.statement.end.line, nginx.statement.end.column));
+    assert_eq!((9, 5), (nginx.name.start.line, nginx.name.start.column));
+    let tag = nginx.option_location(&modules[2], "tag").unwrap();
+    assert_eq!(tag, nginx.info[1]);
+    assert_eq!((12, 3), (tag.start.line, tag.start.column));
+    assert_eq!(None, nginx.version_location(&modules[2]));
+    assert_eq!(None, nginx.option_location(&modules[2], "branch"));
+}
+
+#[test]
+fn edit_versions() {
+    let mut tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    tree.set_version("puppetlabs/stdlib", "4.10.0").unwrap();
+    tree.set_version("puppetlabs/apache", "1.2.0").unwrap();
+    assert_eq!(CONTROL_REPO.replace("'4.9.0'", "'4.10.0'").replace(":latest", "\"1.2.0\""),
+               tree.to_string());
+    assert_eq!(Some(&VersionReq::parse("= 4.10.0").unwrap()),
+               tree.to_puppetfile().unwrap().modules[0].version());
+
+    let unchanged = tree.clone();
+    assert!(tree.set_version("puppetlabs/stdlib", "1.x.y.z").is_err());
+    for name in &["site", "nginx"] {
+        match tree.set_version(name, ">= 1.0.0").unwrap_err().kind {
+            ErrorKind::InvalidSource { ref module, .. } => assert_eq!(*name, &module[..]),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+    match tree.set_version("puppetlabs/nginx", "1.0.0").unwrap_err().kind {
+        ErrorKind::UnknownModule(ref name) => assert_eq!("puppetlabs/nginx", name),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(unchanged, tree);
+}
+
+#[test]
+fn edit_options() {
+    let mut tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    tree.set_option("nginx", "tag", "v0.7.0").unwrap();
+    tree.set_option("nginx", "exclude_spec", Value::Symbol("true".to_string())).unwrap();
+    tree.set_option("site", "install_path", "site").unwrap();
+    assert_eq!(CONTROL_REPO.replace("\"v0.6.0\"", "\"v0.7.0\",\n  exclude_spec: :true")
+                           .replace("'true'", "'true', :install_path => 'site'"),
+               tree.to_string());
+
+    // edits leaving contradicting options behind are rejected
+    let unchanged = tree.clone();
+    for &(name, key) in &[("puppetlabs/stdlib", "git"), ("nginx", "branch"), ("site", "svn")] {
+        match tree.set_option(name, key, "main").unwrap_err().kind {
+            ErrorKind::InvalidSource { ref module, location: Some(..) } => assert_eq!(name, module),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+    assert_eq!(unchanged, tree);
+}
+
+#[test]
+fn edit_modules() {
+    let mut tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    tree.remove_module("puppetlabs/apache").unwrap();
+    tree.remove_module("nginx").unwrap();
+    tree.add_module(&Module {
+            name: "puppetlabs/concat".to_string(),
+            info: vec![],
+        })
+        .unwrap();
+    assert_eq!(r##"# Puppetfile of the control repository
+moduledir 'site-modules'
+forge "https://forge.puppetlabs.com"
+
+# pinned, see OPS-123
+mod 'puppetlabs/stdlib',   '4.9.0' # do not bump
+
+mod 'site', :local => 'true'	# kept locally
+
+mod 'puppetlabs/concat'
+
+"##,
+               tree.to_string());
+    assert!(tree.add_module(&Module {
+                    name: "site".to_string(),
+                    info: vec![],
+                })
+                .is_err());
+
+    let mut tree = SyntaxTree::parse("mod 'a'\nmod 'b'\n").unwrap();
+    tree.remove_module("a").unwrap();
+    assert_eq!("mod 'b'\n", tree.to_string());
+    tree.remove_module("b").unwrap();
+    tree.add_module(&Module {
+            name: "c".to_string(),
+            info: vec![],
+        })
+        .unwrap();
+    assert_eq!("mod 'c'\n", tree.to_string());
+
+    let mut tree = SyntaxTree::parse("forge 'f'\n\n# Section: forge modules\n\n# utilities\n\
+                                      mod 'a'\n\nmod 'b' # last\n")
+                       .unwrap();
+    tree.remove_module("a").unwrap();
+    assert_eq!("forge 'f'\n\n# Section: forge modules\n\nmod 'b' # last\n",
+               tree.to_string());
+    tree.add_module(&Module {
+            name: "c".to_string(),
+            info: vec![],
+        })
+        .unwrap();
+    assert_eq!("forge 'f'\n\n# Section: forge modules\n\nmod 'b' # last\n\nmod 'c'\n",
+               tree.to_string());
+    tree.remove_module("b").unwrap();
+    assert_eq!("forge 'f'\n\n# Section: forge modules\n\nmod 'c'\n", tree.to_string());
+}
+
+#[test]
+fn format_control_repo() {
+    let tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    let formatted = tree.format(&FormatStyle::default());
+    assert_eq!(r##"# Puppetfile of the control repository
+forge 'https://forge.puppetlabs.com'
+moduledir 'site-modules'
+
+# pinned, see OPS-123
+mod 'puppetlabs/stdlib', '4.9.0' # do not bump
+
+mod 'puppetlabs/apache', :latest
+
+mod 'nginx',
+  :git => 'https://github.com/voxpupuli/puppet-nginx.git', # fork
+  # the tag we tested
+  tag: 'v0.6.0'
+
+mod 'site',
+  :local => 'true' # kept locally
+"##,
+               formatted);
+    let reformatted = SyntaxTree::parse(&formatted).unwrap();
+    assert_eq!(formatted, reformatted.format(&FormatStyle::default()));
+    assert_eq!(tree.to_puppetfile().unwrap(), reformatted.to_puppetfile().unwrap());
+
+    let style = FormatStyle {
+        quote: '"',
+        hash_style: Some(HashStyle::HashRocket),
+        blank_lines: 0,
+        ..FormatStyle::default()
+    };
+    let tree = SyntaxTree::parse("mod 'nginx', :git => 'https://github.com/voxpupuli/puppet-nginx.git', \
+                                  tag: 'v0.6.0', :install_path => \"it's\"\n\
+                                  mod 'site'   # local\n\n\n\
+                                  # the end\n\n")
+                   .unwrap();
+    assert_eq!(r##"mod "nginx",
+  :git          => "https://github.com/voxpupuli/puppet-nginx.git",
+  :tag          => "v0.6.0",
+  :install_path => "it's"
+mod "site" # local
+
+# the end
+"##,
+               tree.format(&style));
+
+    assert_eq!("   2 -mod 'b'\n   2 +mod 'c'\n   3 +mod 'd'\n",
+               line_diff("mod 'a'\nmod 'b'\n", "mod 'a'\nmod 'c'\nmod 'd'\n"));
+}
+
+#[test]
+fn sort_modules() {
+    let contents = r##"# managed by the platform team
+
+forge 'https://forge.puppetlabs.com'
+
+mod 'site', :local => 'true'
+# pinned, see OPS-123
+mod 'puppetlabs-stdlib', '4.9.0' # do not bump
+mod 'nginx',
+  :git => 'https://github.com/voxpupuli/puppet-nginx.git'
+
+# Apache
+mod 'puppetlabs/apache', :latest
+"##;
+    let mut puppetfile = Puppetfile::parse(contents).unwrap();
+    puppetfile.sort_modules(SortOrder::Name);
+    assert_eq!(vec!["nginx", "puppetlabs/apache", "puppetlabs-stdlib", "site"],
+               puppetfile.modules.iter().map(|m| &m.name[..]).collect::<Vec<_>>());
+    puppetfile.sort_modules(SortOrder::SourceAndName);
+    assert_eq!(vec!["puppetlabs/apache", "puppetlabs-stdlib", "nginx", "site"],
+               puppetfile.modules.iter().map(|m| &m.name[..]).collect::<Vec<_>>());
+
+    let mut tree = SyntaxTree::parse(contents).unwrap();
+    tree.sort_modules(SortOrder::SourceAndName).unwrap();
+    assert_eq!(r##"# managed by the platform team
+
+forge 'https://forge.puppetlabs.com'
+
+# Apache
+mod 'puppetlabs/apache', :latest
+# pinned, see OPS-123
+mod 'puppetlabs-stdlib', '4.9.0' # do not bump
+mod 'nginx',
+  :git => 'https://github.com/voxpupuli/puppet-nginx.git'
+
+mod 'site', :local => 'true'
+"##,
+               tree.to_string());
+    assert_eq!(puppetfile, tree.to_puppetfile().unwrap());
+}
+
+#[test]
+fn lints() {
+    let contents = r##"forge 'http://forge.example.com'
+
+mod 'puppetlabs/stdlib', '4.9.0'
+mod 'puppetlabs-stdlib', '4.10.0'
+mod 'puppetlabs/apache', '>= 1.0.0'
+mod 'puppetlabs/concat' # puppetfile:disable=unpinned-forge-module
+mod 'nginx',
+  :git => 'git://github.com/voxpupuli/puppet-nginx.git',
+  :branch => 'master',
+  :verify => 'true'
+# puppetfile:disable=all
+mod 'site', :git => 'http://git.example.com/site.git'
+mod 'apt', :git => 'https://github.com/puppetlabs/puppetlabs-apt.git', :tag => 'v1',
+  :commit => 'cafe'
+"##;
+    let lints = lint::lint(contents, &LintConfig::default()).unwrap();
+    assert_eq!(vec![("insecure-forge", Severity::Warning, 1, 1),
+                    ("duplicate-module", Severity::Error, 4, 1),
+                    ("unpinned-forge-module", Severity::Warning, 5, 26),
+                    ("unknown-option", Severity::Warning, 10, 3),
+                    ("insecure-url", Severity::Error, 8, 3),
+                    ("git-branch", Severity::Warning, 9, 3),
+                    ("conflicting-options", Severity::Error, 13, 1)],
+               lints.iter()
+                    .map(|lint| {
+                        let start = lint.location.unwrap().start;
+                        (lint.rule, lint.severity, start.line, start.column)
+                    })
+                    .collect::<Vec<_>>());
+    assert_eq!("error[duplicate-module]: module 'puppetlabs-stdlib' is already declared as \
+                'puppetlabs/stdlib' at 4:1",
+               lints[1].to_string());
+    assert_eq!(r##"warning[git-branch]: module 'nginx' follows the branch 'master'
+ --> Puppetfile:9:3
+  |
+9 |   :branch => 'master',
+  |   ^^^^^^^
+"##,
+               Renderer::new("Puppetfile", contents).render_lint(&lints[5]));
+
+    let config = LintConfig::parse(r##"[rules]
+# forge modules may follow ranges
+unpinned-forge-module = "off"
+insecure-forge = "error"
+git-branch = warning
+"##)
+                     .unwrap();
+    assert_eq!(None, config.severity("unpinned-forge-module"));
+    let puppetfile = Puppetfile::parse(contents).unwrap();
+    let lints = lint::lint_puppetfile(&puppetfile, &config);
+    assert_eq!(vec!["insecure-forge", "duplicate-module", "unknown-option", "insecure-url",
+                    "git-branch", "insecure-url", "git-branch", "conflicting-options"],
+               lints.iter().map(|lint| lint.rule).collect::<Vec<_>>());
+    assert_eq!(Severity::Error, lints[0].severity);
+    assert_eq!(None, lints[0].location);
+
+    match LintConfig::parse("git-branch = off\nno-such-rule = error").unwrap_err().kind {
+        ErrorKind::InvalidLintConfig(2) => (),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert!(LintConfig::parse("git-branch = loud").is_err());
+
+    let config = LintConfig::parse("git-branch = \"off\" # was \"error\"").unwrap();
+    assert_eq!(None, config.severity("git-branch"));
+    // `#` inside quotes is part of the value, not a comment
+    match LintConfig::parse("[rules]\ngit-branch = \"off#1\"").unwrap_err().kind {
+        ErrorKind::InvalidLintConfig(2) => (),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert!(LintConfig::parse("git-branch = \"off").is_err());
+    assert!(LintConfig::parse("[checks]").is_err());
+}
+
+#[test]
+fn collisions() {
+    let contents = r##"mod 'puppetlabs/apache', '1.0.0'
+mod 'puppetlabs/stdlib', '4.9.0'
+mod 'example/apache',
+  :git => 'https://git.example.com/apache.git'
+mod 'example/stdlib', :install_path => 'vendor'
+mod 'puppetlabs-stdlib', '4.10.0'
+"##;
+    let (puppetfile, collisions) = Puppetfile::validate(contents).unwrap();
+    assert_eq!(Path::new("vendor/stdlib"), puppetfile.install_path(&puppetfile.modules[3]));
+    assert_eq!(vec![(CollisionKind::InstallPath, "puppetlabs/apache", 1, "example/apache", 3),
+                    (CollisionKind::Duplicate, "puppetlabs/stdlib", 2, "puppetlabs-stdlib", 6)],
+               collisions.iter()
+                         .map(|c| {
+                             (c.kind,
+                              &c.first[..],
+                              c.first_location.unwrap().start.line,
+                              &c.second[..],
+                              c.second_location.unwrap().start.line)
+                         })
+                         .collect::<Vec<_>>());
+    assert_eq!("module 'puppetlabs-stdlib' is already declared as 'puppetlabs/stdlib' \
+                (duplicate at 6:5, original at 2:5)",
+               collisions[1].to_string());
+    assert_eq!("module 'example/apache' installs into 'modules/apache' like 'puppetlabs/apache' \
+                (declared at 3:5, 'puppetlabs/apache' declared at 1:5)",
+               collisions[0].to_string());
+    assert_eq!(r##"warning: module 'example/apache' installs into 'modules/apache' like 'puppetlabs/apache'
+ --> Puppetfile:3:5
+  |
+3 | mod 'example/apache',
+  |     ^^^^^^^^^^^^^^^^
+note: 'puppetlabs/apache' is declared here
+ --> Puppetfile:1:5
+  |
+1 | mod 'puppetlabs/apache', '1.0.0'
+  |     ^^^^^^^^^^^^^^^^^^^
+"##,
+               Renderer::new("Puppetfile", contents).render_collision(&collisions[0]));
+
+    let without_source = puppetfile.collisions();
+    assert_eq!(2, without_source.len());
+    assert_eq!(None, without_source[0].first_location);
+    assert_eq!("module 'puppetlabs-stdlib' is already declared as 'puppetlabs/stdlib'",
+               without_source[1].to_string());
+
+    // a duplicate wins over an earlier module installing into the same directory
+    let puppetfile = Puppetfile::parse("mod 'b/apache'\nmod 'a/apache'\nmod 'a-apache'\n").unwrap();
+    let collisions = puppetfile.collisions();
+    assert_eq!((CollisionKind::Duplicate, "a/apache"),
+               (collisions[1].kind, &collisions[1].first[..]));
+    let lints = lint::lint_puppetfile(&puppetfile, &LintConfig::default());
+    assert_eq!(vec![collisions[1].message()],
+               lints.iter()
+                    .filter(|lint| lint.rule == "duplicate-module")
+                    .map(|lint| lint.message.clone())
+                    .collect::<Vec<_>>());
+}
+
 #[test]
 fn version_url() {
     let module = Module {
         name: "mayflower/php".to_string(),
         info: vec![],
     };
-    assert_eq!("https://forge.puppetlabs.com/users/mayflower/modules/php/releases/find.json"
-                   .to_string(),
+    assert_eq!("https://forge.puppetlabs.com/v3/modules/mayflower-php".to_string(),
                module.version_url("https://forge.puppetlabs.com/").unwrap())
 }
 
@@ -118,12 +1036,601 @@ fn user_name_pair() {
     assert_eq!(module.user_name_pair(), Some(("mayflower", "php")))
 }
 
+#[test]
+fn module_names() {
+    let slash = ModuleName::parse("puppetlabs/stdlib").unwrap();
+    let dash = ModuleName::parse("puppetlabs-stdlib").unwrap();
+    assert_eq!(slash, dash);
+    assert_eq!(Some("puppetlabs".to_string()), dash.owner);
+    assert_eq!("stdlib", dash.name);
+    assert_eq!("puppetlabs-stdlib", dash.to_string());
+    assert_eq!("puppetlabs/stdlib", slash.to_string());
+    assert_eq!("puppetlabs-stdlib", slash.slug());
+
+    let site: ModuleName = "site_profile".parse().unwrap();
+    assert_eq!(None, site.owner);
+    assert_eq!("site_profile", site.to_string());
+
+    for name in &["", "puppetlabs/", "-stdlib", "puppetlabs/std-lib", "puppet labs/stdlib",
+                  "puppetlabs/stdlib/extra"] {
+        match ModuleName::parse(name).unwrap_err().kind {
+            ErrorKind::InvalidModuleName(ref invalid) => assert_eq!(name, invalid),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    let module = Module {
+        name: "mayflower-php".to_string(),
+        info: vec![],
+    };
+    assert_eq!(module.user_name_pair(), Some(("mayflower", "php")));
+    assert_eq!("php", module.install_name());
+    assert_eq!("https://forge.puppetlabs.com/v3/modules/mayflower-php",
+               module.version_url("https://forge.puppetlabs.com").unwrap());
+
+    let mut tree = SyntaxTree::parse(CONTROL_REPO).unwrap();
+    tree.set_version("puppetlabs-stdlib", "4.10.0").unwrap();
+    assert!(tree.to_string().contains("mod 'puppetlabs/stdlib',   '4.10.0'"));
+}
+
+/// Serves canned JSON for the given paths until the test ends, returns the URL of the server
+fn mock_forge(routes: Vec<(&'static str, &'static str)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or("");
+            let (status, body) = match routes.iter().find(|route| route.0 == path) {
+                Some(route) => ("200 OK", route.1),
+                None => ("404 Not Found", r#"{"message":"404 Not Found","errors":[]}"#),
+            };
+            write!(stream,
+                   "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                    Connection: close\r\n\r\n{}",
+                   status,
+                   body.len(),
+                   body)
+                .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn forge_redirects() {
+    // a redirect must not carry the token to another host
+    let target = TcpListener::bind("127.0.0.1:0").unwrap();
+    let location = format!("http://{}/v3/modules/puppetlabs-stdlib",
+                           target.local_addr().unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            write!(stream,
+                   "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\
+                    Connection: close\r\n\r\n",
+                   location)
+                .unwrap();
+        }
+    });
+    let auth = ForgeAuth { token: Some(Token::new("s3cret")), ..ForgeAuth::default() };
+    let client = ForgeClient::new(&url).with_retry(RetryPolicy::none()).with_auth(auth);
+    match client.module(&ModuleName::parse("puppetlabs/stdlib").unwrap()).unwrap_err().kind {
+        ErrorKind::HttpStatus { status, .. } => assert_eq!(302, status),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    target.set_nonblocking(true).unwrap();
+    assert!(target.accept().is_err());
+}
+
+#[test]
+fn forge_client() {
+    let url = mock_forge(vec![("/v3/modules/puppetlabs-stdlib",
+                               include_str!("fixtures/forge/puppetlabs-stdlib.json")),
+                              ("/v3/modules/puppetlabs-firewall",
+                               include_str!("fixtures/forge/puppetlabs-firewall.json")),
+                              ("/v3/releases?module=puppetlabs-stdlib&limit=100",
+                               include_str!("fixtures/forge/puppetlabs-stdlib-releases.json")),
+                              ("/v3/releases?module=puppetlabs-stdlib&limit=2&offset=2",
+                               include_str!("fixtures/forge/puppetlabs-stdlib-releases-2.json"))]);
+    let client = ForgeClient::new(&format!("{}/", url));
+    let stdlib = ModuleName::parse("puppetlabs/stdlib").unwrap();
+
+    let module = client.module(&stdlib).unwrap();
+    assert_eq!("puppetlabs-stdlib", module.slug);
+    assert_eq!("puppetlabs", module.owner.username);
+    assert!(!module.is_deprecated());
+    assert_eq!(vec!["4.12.0", "4.11.0", "4.10.0"],
+               module.releases.iter().map(|release| &release.version[..]).collect::<Vec<_>>());
+    let current = module.current_release;
+    assert_eq!(semver::Version::parse("4.12.0").unwrap(), current.version().unwrap());
+    assert_eq!("/v3/files/puppetlabs-stdlib-4.12.0.tar.gz", current.file_uri);
+    assert_eq!("7ba6a5b2b6b2a9e8d1b7d5b3a1c2e4f6", current.file_md5);
+    assert_eq!(Some(64), current.file_sha256.as_ref().map(|sha| sha.len()));
+    assert_eq!("puppetlabs-stdlib", current.metadata.name);
+    assert_eq!(Some("Apache-2.0".to_string()), current.metadata.license);
+    assert_eq!(Some(vec![]), current.metadata.dependencies);
+
+    let releases = client.releases(&stdlib).unwrap();
+    assert_eq!(vec!["4.12.0", "4.11.0", "4.10.0"],
+               releases.iter().map(|release| &release.metadata.version[..]).collect::<Vec<_>>());
+    assert_eq!(None, releases[2].file_sha256);
+
+    let firewall = client.module(&ModuleName::parse("puppetlabs-firewall").unwrap()).unwrap();
+    assert!(firewall.is_deprecated());
+    assert_eq!("puppet-firewall", firewall.superseded_by.unwrap().slug);
+
+    let module = Module {
+        name: "puppetlabs/stdlib".to_string(),
+        info: vec![],
+    };
+    assert_eq!(semver::Version::parse("4.12.0").unwrap(),
+               module.forge_version(&url).unwrap());
+
+    match client.module(&ModuleName::parse("puppetlabs/nginx").unwrap()).unwrap_err().kind {
+        ErrorKind::ModuleNotFound { ref module, ref forge, ref suggestions } => {
+            assert_eq!("puppetlabs/nginx", module);
+            assert_eq!(&url, forge);
+            assert!(suggestions.is_empty());
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    match client.module(&ModuleName::parse("site").unwrap()).unwrap_err().kind {
+        ErrorKind::UrlBuilding => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn find_releases() {
+    let transport = FakeTransport::new()
+                        .with_json("/v3/releases?module=puppetlabs-stdlib&limit=100",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib-releases.json"))
+                        .with_json("/v3/releases?module=puppetlabs-stdlib&limit=2&offset=2",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib-releases-2.json"))
+                        .with_json(&format!("{}&show_deleted=true",
+                                            "/v3/releases?module=puppetlabs-stdlib&limit=100"),
+                                   include_str!("fixtures/forge/\
+                                                 puppetlabs-stdlib-releases-deleted.json"));
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com", transport);
+    let stdlib = ModuleName::parse("puppetlabs-stdlib").unwrap();
+    let versions = |filter: &ReleaseFilter| -> Vec<String> {
+        client.find_releases(&stdlib, filter)
+              .unwrap()
+              .into_iter()
+              .map(|release| release.version)
+              .collect()
+    };
+
+    assert_eq!(vec!["4.12.0", "4.11.0", "4.10.0"], versions(&ReleaseFilter::default()));
+
+    let puppetfile = Puppetfile::parse("mod 'puppetlabs/stdlib', '~4.10.0'\n\
+                                        mod 'puppetlabs/apache'")
+                         .unwrap();
+    assert_eq!(vec!["4.10.0"],
+               versions(&ReleaseFilter::for_module(&puppetfile.modules[0])));
+    assert_eq!(vec!["4.12.0", "4.11.0", "4.10.0"],
+               versions(&ReleaseFilter::for_module(&puppetfile.modules[1])));
+
+    let mut filter = ReleaseFilter {
+        requirement: Some(VersionReq::parse(">= 4.11.0").unwrap()),
+        ..ReleaseFilter::default()
+    };
+    assert_eq!(vec!["4.12.0", "4.11.0"], versions(&filter));
+
+    filter.requirement = None;
+    filter.deleted = true;
+    assert_eq!(vec!["4.12.0", "4.9.0"], versions(&filter));
+    filter.pre_releases = true;
+    assert_eq!(vec!["5.0.0-rc1", "4.12.0", "4.9.0"], versions(&filter));
+    filter.deleted = false;
+    assert_eq!(vec!["4.12.0", "4.11.0", "4.10.0"], versions(&filter));
+}
+
+#[test]
+fn check_versions() {
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules/puppetlabs-stdlib",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib.json"))
+                        .with_json("/v3/modules/puppetlabs-firewall",
+                                   include_str!("fixtures/forge/puppetlabs-firewall.json"));
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com", transport);
+    let client = Arc::new(client);
+    let puppetfile = Puppetfile::parse("mod 'puppetlabs/stdlib', '4.12.0'
+mod 'puppetlabs/nginx'
+mod 'site', :git => 'https://git.example.com/site.git'
+mod 'puppetlabs/firewall', '~1.7'")
+                         .unwrap();
+
+    for &concurrency in &[1, 2, 16] {
+        let checks = puppetfile.check_versions_with(client.clone(), concurrency);
+        let names: Vec<&str> = checks.iter().map(|check| &check.module.name[..]).collect();
+        assert_eq!(vec!["puppetlabs/stdlib", "puppetlabs/nginx", "puppetlabs/firewall"], names);
+        let version = semver::Version::parse("4.12.0").unwrap();
+        assert_eq!(&version, checks[0].latest.as_ref().unwrap());
+        assert_eq!(Some(false), checks[0].is_outdated());
+        match checks[1].latest.as_ref().unwrap_err().kind {
+            ErrorKind::ModuleNotFound { .. } => {}
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(None, checks[1].is_outdated());
+        assert_eq!(Some(true), checks[2].is_outdated());
+    }
+    // one lookup per module, a search and an owner search for suggestions for the missing one
+    assert_eq!(15, client.transport().requested_urls().len());
+
+    // a panicking lookup becomes an error and the other lookups still finish
+    struct PanickingTransport(FakeTransport);
+    impl Transport for PanickingTransport {
+        fn send(&self, request: &Request) -> Result<Response, PuppetfileError> {
+            if request.url.contains("nginx") {
+                panic!("lookup failed");
+            }
+            self.0.send(request)
+        }
+    }
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules/puppetlabs-stdlib",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib.json"))
+                        .with_json("/v3/modules/puppetlabs-firewall",
+                                   include_str!("fixtures/forge/puppetlabs-firewall.json"));
+    let transport = PanickingTransport(transport);
+    let client = Arc::new(ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                                      transport));
+    for &concurrency in &[1, 2] {
+        let checks = puppetfile.check_versions_with(client.clone(), concurrency);
+        assert_eq!(3, checks.len());
+        assert!(checks[0].latest.is_ok());
+        match checks[1].latest.as_ref().unwrap_err().kind {
+            ErrorKind::LookupPanicked(ref module) => assert_eq!("puppetlabs/nginx", module),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert!(checks[2].latest.is_ok());
+    }
+}
+
+#[test]
+fn forge_cache() {
+    let dir = temp_dir("forge-cache");
+    let stdlib = ModuleName::parse("puppetlabs-stdlib").unwrap();
+    let firewall = ModuleName::parse("puppetlabs-firewall").unwrap();
+    let forge = |ttl: u64| {
+        let transport = FakeTransport::new()
+                            .with_json("/v3/modules/puppetlabs-stdlib",
+                                       include_str!("fixtures/forge/puppetlabs-stdlib.json"))
+                            .with_header("/v3/modules/puppetlabs-stdlib", "ETag", "\"v1\"");
+        let caching = CachingTransport::new(transport, &dir).with_ttl(Duration::from_secs(ttl));
+        ForgeClient::with_transport("https://forgeapi.puppetlabs.com", caching)
+    };
+
+    let client = forge(3600);
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    assert_eq!(1, client.transport().inner().requests().len());
+    let path = client.transport()
+                     .cache_path("https://forgeapi.puppetlabs.com/v3/modules/puppetlabs-stdlib");
+    assert_eq!(dir.join("forgeapi.puppetlabs.com").join("%2Fv3%2Fmodules%2Fpuppetlabs-stdlib"),
+               path);
+    assert!(path.exists());
+    // the entry is written to a temporary file first, which is renamed
+    assert_eq!(1, fs::read_dir(path.parent().unwrap()).unwrap().count());
+
+    let client = forge(0);
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    let requests = client.transport().inner().requests();
+    assert_eq!(Some("\"v1\""), requests[0].header("if-none-match"));
+
+    let stale = FakeTransport::new().with_response("/v3/modules/puppetlabs-stdlib", 503, "");
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                             CachingTransport::new(stale, &dir)
+                                                 .with_ttl(Duration::from_secs(0)));
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    let limited = FakeTransport::new().with_response("/v3/modules/puppetlabs-stdlib", 429, "");
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                             CachingTransport::new(limited, &dir)
+                                                 .with_ttl(Duration::from_secs(0)));
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+
+    let offline = CachingTransport::new(FakeTransport::new(), &dir).with_offline(true);
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com", offline);
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    match client.module(&firewall).unwrap_err().kind {
+        ErrorKind::NotCached(ref url) => {
+            assert_eq!("https://forgeapi.puppetlabs.com/v3/modules/puppetlabs-firewall", url)
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert!(client.transport().inner().requests().is_empty());
+
+    // authenticated requests are cached per credential
+    let firewall_url = "https://forgeapi.puppetlabs.com/v3/modules/puppetlabs-firewall";
+    let authenticated = |token: &str, offline: bool| {
+        let transport = FakeTransport::new()
+                            .with_json("/v3/modules/puppetlabs-firewall",
+                                       include_str!("fixtures/forge/puppetlabs-firewall.json"));
+        let auth = ForgeAuth { token: Some(Token::new(token)), ..ForgeAuth::default() };
+        ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                    CachingTransport::new(transport, &dir).with_offline(offline))
+            .with_auth(auth)
+    };
+    let client = authenticated("s3cret", false);
+    client.module(&firewall).unwrap();
+    client.module(&firewall).unwrap();
+    assert_eq!(1, client.transport().inner().requests().len());
+    assert!(!client.transport().cache_path(firewall_url).exists());
+    let path = client.transport().credential_cache_path(firewall_url, "Bearer s3cret");
+    assert!(path.exists());
+    assert!(!path.to_string_lossy().contains("s3cret"));
+    let client = authenticated("other", false);
+    client.module(&firewall).unwrap();
+    assert_eq!(1, client.transport().inner().requests().len());
+    let client = authenticated("s3cret", true);
+    client.module(&firewall).unwrap();
+    assert!(client.transport().inner().requests().is_empty());
+    match authenticated("s3cret", true).module(&stdlib).unwrap_err().kind {
+        ErrorKind::NotCached(..) => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+
+    // a cache that can not be written does not fail the request
+    let file = dir.join("not-a-directory");
+    fs::File::create(&file).unwrap();
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules/puppetlabs-stdlib",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib.json"));
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                             CachingTransport::new(transport, &file));
+    assert_eq!("4.12.0", client.module(&stdlib).unwrap().current_release.version);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A new directory for a test, unique across test threads and processes
+fn temp_dir(name: &str) -> PathBuf {
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+    let dir = env::temp_dir().join(format!("puppetfile-{}-{}-{}",
+                                           name,
+                                           process::id(),
+                                           DIRS.fetch_add(1, Ordering::SeqCst)));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn forge_credentials() {
+    env::set_var("PUPPETFILE_TEST_FORGE_TOKEN", "s3cret-from-env");
+    let credentials = ForgeCredentials::parse(r#"
+# private forges
+["https://forge.example.com/"]
+token = "s3cret"
+user_agent = "deploy/1.0"
+
+["https://artifactory.example.com/api/puppet"]
+token_env = "PUPPETFILE_TEST_FORGE_TOKEN"
+"#)
+                          .unwrap();
+    let private = credentials.get("https://forge.example.com");
+    assert_eq!("s3cret", private.token.as_ref().unwrap().secret());
+    assert!(!format!("{:?}", credentials).contains("s3cret"));
+    assert_eq!("s3cret-from-env",
+               credentials.get("https://artifactory.example.com/api/puppet")
+                          .token
+                          .unwrap()
+                          .secret());
+    assert_eq!(None, credentials.get("https://forgeapi.puppetlabs.com").token);
+    let credentials = ForgeCredentials::parse("[\"https://forge.example.com/#private\"]
+token = \"s3c#ret\" # a comment").unwrap();
+    let token = credentials.get("https://forge.example.com/#private").token;
+    assert_eq!("s3c#ret", token.unwrap().secret());
+
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules/puppetlabs-stdlib",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib.json"));
+    let client = ForgeClient::with_transport("https://forge.example.com", transport)
+                     .with_auth(private);
+    let nginx = ModuleName::parse("puppetlabs/nginx").unwrap();
+    let err = client.module(&nginx).unwrap_err();
+    assert!(!format!("{:?} {}", err, err).contains("s3cret"));
+    client.module(&ModuleName::parse("puppetlabs/stdlib").unwrap()).unwrap();
+    let requests = client.transport().requests();
+    for request in requests.iter() {
+        assert_eq!(Some("Bearer s3cret"), request.header("Authorization"));
+        assert_eq!(Some("deploy/1.0"), request.header("User-Agent"));
+    }
+    assert!(!format!("{:?}", requests).contains("s3cret"));
+
+    for &(config, line) in &[("token = \"s3cret\"", 1),
+                             ("[\"https://forge.example.com\"]\ntokn = \"s3cret\"", 2),
+                             ("[\"https://forge.example.com\"]\ntoken_env = \"PUPPETFILE_UNSET\"",
+                              2),
+                             ("[\"https://forge.example.com\"]\ntoken = \"s3cret", 2)] {
+        let err = ForgeCredentials::parse(config).unwrap_err();
+        match err.kind {
+            ErrorKind::InvalidForgeConfig(invalid) => assert_eq!(line, invalid),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert!(!err.desc.contains("s3cret"));
+    }
+}
+
+#[test]
+fn forge_retries() {
+    let stdlib = ModuleName::parse("puppetlabs-stdlib").unwrap();
+    let path = "/v3/modules/puppetlabs-stdlib";
+    let retry = RetryPolicy {
+        retries: 2,
+        base_delay: Duration::from_millis(0),
+        max_delay: Duration::from_secs(30),
+    };
+    let forge = |transport: FakeTransport| {
+        ForgeClient::with_transport("https://forgeapi.puppetlabs.com", transport).with_retry(retry)
+    };
+
+    let client = forge(FakeTransport::new()
+                           .with_response(path, 429, "")
+                           .with_header(path, "Retry-After", "0")
+                           .with_response(path, 502, "<html>Bad Gateway</html>")
+                           .with_json(path, include_str!("fixtures/forge/puppetlabs-stdlib.json")));
+    assert_eq!("puppetlabs-stdlib", client.module(&stdlib).unwrap().slug);
+    assert_eq!(3, client.transport().requests().len());
+
+    let client = forge(FakeTransport::new().with_response(path, 503, ""));
+    match client.module(&stdlib).unwrap_err().kind {
+        ErrorKind::ServerError { status, .. } => assert_eq!(503, status),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(3, client.transport().requests().len());
+
+    let client = forge(FakeTransport::new()
+                           .with_response(path, 429, "")
+                           .with_header(path, "Retry-After", "120"));
+    match client.module(&stdlib).unwrap_err().kind {
+        ErrorKind::RateLimited { retry_after, .. } => assert_eq!(Some(120), retry_after),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(1, client.transport().requests().len());
+
+    let client = forge(FakeTransport::new().with_response(path, 403, ""));
+    match client.module(&stdlib).unwrap_err().kind {
+        ErrorKind::HttpStatus { status, .. } => assert_eq!(403, status),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(1, client.transport().requests().len());
+
+    // connection errors are retried, local errors are not
+    struct FailingTransport(io::ErrorKind, Cell<usize>);
+    impl Transport for FailingTransport {
+        fn send(&self, _: &Request) -> Result<Response, PuppetfileError> {
+            self.1.set(self.1.get() + 1);
+            Err(From::from(io::Error::new(self.0, "failed")))
+        }
+    }
+    for &(kind, attempts) in &[(io::ErrorKind::ConnectionReset, 3),
+                               (io::ErrorKind::TimedOut, 3),
+                               (io::ErrorKind::PermissionDenied, 1)] {
+        let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com",
+                                                 FailingTransport(kind, Cell::new(0)))
+                         .with_retry(retry);
+        match client.module(&stdlib).unwrap_err().kind {
+            ErrorKind::IoError(ref err) => assert_eq!(kind, err.kind()),
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(attempts, client.transport().1.get());
+    }
+
+    assert_eq!(None, RetryPolicy::none().delay(0, None));
+    let policy = RetryPolicy::default();
+    for attempt in 0..3 {
+        let delay = policy.delay(attempt, None).unwrap();
+        assert!(delay >= Duration::from_millis(250 << attempt));
+        assert!(delay <= Duration::from_millis(500 << attempt));
+    }
+    // clients retrying at the same moment wait for different delays
+    let delays: Vec<Duration> = (0..20).map(|_| policy.delay(2, None).unwrap()).collect();
+    assert!(delays.iter().any(|&delay| delay != delays[0]));
+    assert_eq!(Some(Duration::from_secs(5)), policy.delay(0, Some(5)));
+    assert_eq!(None, policy.delay(3, None));
+}
+
+#[test]
+fn module_not_found() {
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules?query=stdlb&limit=20",
+                                   r#"{
+  "pagination": {"limit": 20, "offset": 0, "next": null, "total": 3},
+  "results": [
+    {"slug": "puppetlabs-apache", "name": "apache"},
+    {"slug": "puppet-stdlib", "name": "stdlib"},
+    {"slug": "puppetlabs-stdlib", "name": "stdlib"}
+  ]
+}"#)
+                        .with_json("/v3/modules?query=ngnix&limit=20",
+                                   r#"{
+  "pagination": {"limit": 20, "offset": 0, "next": null, "total": 0},
+  "results": []
+}"#)
+                        .with_json("/v3/modules?owner=puppetlabs&limit=100",
+                                   r#"{
+  "pagination": {"limit": 100, "offset": 0, "next": null, "total": 3},
+  "results": [
+    {"slug": "puppetlabs-apache", "name": "apache"},
+    {"slug": "puppetlabs-nginx", "name": "nginx"},
+    {"slug": "puppetlabs-stdlib", "name": "stdlib"}
+  ]
+}"#);
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com", transport);
+    let module = Module {
+        name: "puppetlabs/stdlb".to_string(),
+        info: vec![],
+    };
+    let err = module.forge_version_with(&client).unwrap_err();
+    assert_eq!("module 'puppetlabs/stdlb' was not found on https://forgeapi.puppetlabs.com, \
+                did you mean puppetlabs-stdlib, puppet-stdlib?",
+               err.desc);
+    match err.kind {
+        ErrorKind::ModuleNotFound { ref module, ref forge, ref suggestions } => {
+            assert_eq!("puppetlabs/stdlb", module);
+            assert_eq!("https://forgeapi.puppetlabs.com", forge);
+            assert_eq!(vec!["puppetlabs-stdlib", "puppet-stdlib"], *suggestions);
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+
+    // the search does not find typos, the modules of the owner do
+    let typo = ModuleName::parse("puppetlabs-ngnix").unwrap();
+    assert_eq!(vec!["puppetlabs-nginx"], client.suggestions(&typo));
+    match client.module(&typo).unwrap_err().kind {
+        ErrorKind::ModuleNotFound { ref suggestions, .. } => {
+            assert_eq!(vec!["puppetlabs-nginx"], *suggestions)
+        }
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+
+    let odd = ModuleName {
+        owner: None,
+        name: "a&b c".to_string(),
+        separator: '-',
+    };
+    assert!(client.suggestions(&odd).is_empty());
+    assert_eq!("https://forgeapi.puppetlabs.com/v3/modules?query=a%26b+c&limit=20",
+               client.transport().requested_urls().last().unwrap());
+}
+
 #[test]
 fn forge_version() {
+    let transport = FakeTransport::new()
+                        .with_json("/v3/modules/puppetlabs-stdlib",
+                                   include_str!("fixtures/forge/puppetlabs-stdlib.json"));
+    let client = ForgeClient::with_transport("https://forgeapi.puppetlabs.com/", transport);
     let module = Module {
+        name: "puppetlabs/stdlib".to_string(),
+        info: vec![],
+    };
+    assert_eq!(module.forge_version_with(&client).unwrap(),
+               semver::Version::parse("4.12.0").unwrap());
+    assert_eq!(vec!["https://forgeapi.puppetlabs.com/v3/modules/puppetlabs-stdlib"],
+               client.transport().requested_urls());
+
+    let nginx = Module {
         name: "puppetlabs/nginx".to_string(),
         info: vec![],
     };
-    assert_eq!(module.forge_version(&"https://forge.puppetlabs.com/".to_string()).unwrap(),
-               semver::Version::parse("99.99.99").unwrap())
+    match nginx.forge_version_with(&client).unwrap_err().kind {
+        ErrorKind::ModuleNotFound { .. } => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
 }
@@ -0,0 +1,276 @@
+//! How the forge client talks HTTP
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hyper::client::{Client, RedirectPolicy};
+use hyper::header::{Headers, UserAgent};
+
+use super::PuppetfileError;
+
+/// The `User-Agent` sent unless the request has one
+pub const DEFAULT_USER_AGENT: &'static str = "puppetfile-rs";
+
+/// The seconds `ForgeClient::new` waits for a read or write before failing the request
+pub const DEFAULT_TIMEOUT: u64 = 30;
+
+/// A GET request, `Debug` does not print the `Authorization` header
+#[derive(PartialEq, Clone)]
+pub struct Request {
+    /// The requested URL
+    pub url: String,
+    /// Additional headers as name and value
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// A request for the URL without additional headers
+    pub fn get(url: &str) -> Request {
+        Request {
+            url: url.to_string(),
+            headers: vec![],
+        }
+    }
+
+    /// Adds a header
+    pub fn with_header(mut self, name: &str, value: &str) -> Request {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The value of the header, names are case insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+impl fmt::Debug for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self.headers
+                                             .iter()
+                                             .map(|&(ref name, ref value)| {
+                                                 if name.to_lowercase() == "authorization" {
+                                                     (&name[..], "<redacted>")
+                                                 } else {
+                                                     (&name[..], &value[..])
+                                                 }
+                                             })
+                                             .collect();
+        f.debug_struct("Request")
+         .field("url", &self.url)
+         .field("headers", &headers)
+         .finish()
+    }
+}
+
+/// The answer to a request
+#[derive(PartialEq, Clone, Debug)]
+pub struct Response {
+    /// The HTTP status code, e.g. 200
+    pub status: u16,
+    /// The headers as name and value
+    pub headers: Vec<(String, String)>,
+    /// The body of the response
+    pub body: String,
+}
+
+impl Response {
+    /// Whether the status is a 2xx status
+    pub fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    /// The value of the header, names are case insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter()
+           .find(|header| header.0.to_lowercase() == name.to_lowercase())
+           .map(|header| &header.1[..])
+}
+
+/// Sends the HTTP requests of a `ForgeClient`
+pub trait Transport {
+    /// Sends the request
+    fn send(&self, request: &Request) -> Result<Response, PuppetfileError>;
+}
+
+/// Sends requests with a hyper `Client`
+pub struct HyperTransport {
+    client: Client,
+}
+
+impl HyperTransport {
+    /// Uses a hyper `Client` that does not follow redirects
+    ///
+    /// hyper sends the same headers to the redirect target, so following a redirect would
+    /// leak the `Authorization` header to another host. A redirect fails the request.
+    pub fn new() -> HyperTransport {
+        HyperTransport::with_client(client())
+    }
+
+    /// Like `new`, but fails reads and writes taking longer than `timeout`
+    pub fn with_timeout(timeout: Duration) -> HyperTransport {
+        let mut client = client();
+        client.set_read_timeout(Some(timeout));
+        client.set_write_timeout(Some(timeout));
+        HyperTransport::with_client(client)
+    }
+
+    /// Uses the given client, e.g. one configured with a proxy
+    ///
+    /// Set `RedirectPolicy::FollowNone` on it if requests carry credentials.
+    pub fn with_client(client: Client) -> HyperTransport {
+        HyperTransport { client: client }
+    }
+}
+
+fn client() -> Client {
+    let mut client = Client::new();
+    client.set_redirect_policy(RedirectPolicy::FollowNone);
+    client
+}
+
+impl Default for HyperTransport {
+    fn default() -> HyperTransport {
+        HyperTransport::new()
+    }
+}
+
+impl Transport for HyperTransport {
+    fn send(&self, request: &Request) -> Result<Response, PuppetfileError> {
+        let mut headers = Headers::new();
+        for &(ref name, ref value) in request.headers.iter() {
+            headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+        }
+        if request.header("User-Agent").is_none() {
+            headers.set(UserAgent(DEFAULT_USER_AGENT.to_string()));
+        }
+        let mut response = try!(self.client.get(&request.url).headers(headers).send());
+        let mut body = String::new();
+        try!(response.read_to_string(&mut body));
+        Ok(Response {
+            status: response.status.to_u16(),
+            headers: response.headers
+                             .iter()
+                             .map(|header| (header.name().to_string(), header.value_string()))
+                             .collect(),
+            body: body,
+        })
+    }
+}
+
+/// Serves canned responses by path without touching the network
+///
+/// Paths include the query, e.g. `/v3/releases?module=puppetlabs-stdlib&limit=100`,
+/// unknown paths are answered with 404. Several responses for a path are served in turn,
+/// the last one for all further requests. A request whose `If-None-Match` header matches
+/// the `ETag` of the response is answered with 304.
+#[derive(Default)]
+pub struct FakeTransport {
+    responses: Mutex<HashMap<String, Vec<Response>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl FakeTransport {
+    /// A transport answering every request with 404
+    pub fn new() -> FakeTransport {
+        FakeTransport::default()
+    }
+
+    /// Answers requests for the path with status 200 and the JSON
+    pub fn with_json(self, path: &str, json: &str) -> FakeTransport {
+        self.with_response(path, 200, json)
+    }
+
+    /// Answers requests for the path with the status and body
+    pub fn with_response(self, path: &str, status: u16, body: &str) -> FakeTransport {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_insert(vec![])
+            .push(Response {
+                status: status,
+                headers: vec![],
+                body: body.to_string(),
+            });
+        self
+    }
+
+    /// Adds a header to the last response for the path
+    pub fn with_header(self, path: &str, name: &str, value: &str) -> FakeTransport {
+        if let Some(response) = self.responses
+                                    .lock()
+                                    .unwrap()
+                                    .get_mut(path)
+                                    .and_then(|responses| responses.last_mut()) {
+            response.headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// The requests sent so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The URLs requested so far, oldest first
+    pub fn requested_urls(&self) -> Vec<String> {
+        self.requests().into_iter().map(|request| request.url).collect()
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: &Request) -> Result<Response, PuppetfileError> {
+        self.requests.lock().unwrap().push(request.clone());
+        let mut responses = self.responses.lock().unwrap();
+        let response = responses.get_mut(url_path(&request.url)).map(|responses| {
+            if responses.len() > 1 {
+                responses.remove(0)
+            } else {
+                responses[0].clone()
+            }
+        });
+        match response {
+            Some(response) => {
+                let not_modified = response.header("ETag").is_some() &&
+                                   response.header("ETag") == request.header("If-None-Match");
+                if not_modified {
+                    Ok(Response {
+                        status: 304,
+                        headers: response.headers,
+                        body: String::new(),
+                    })
+                } else {
+                    Ok(response)
+                }
+            }
+            None => {
+                Ok(Response {
+                    status: 404,
+                    headers: vec![],
+                    body: r#"{"message":"404 Not Found","errors":[]}"#.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// The path and query of a URL, `https://host/v3/modules` becomes `/v3/modules`
+pub fn url_path(url: &str) -> &str {
+    let without_scheme = match url.find("://") {
+        Some(i) => &url[i + 3..],
+        None => url,
+    };
+    match without_scheme.find('/') {
+        Some(i) => &without_scheme[i..],
+        None => "/",
+    }
+}